@@ -1,23 +1,95 @@
-// TODO: This file can be extracted to a separate crate.
+// The API client does not depend on ethers, and can be used on its own by disabling the
+// `ethers` feature.
 use crate::{
-    jwtclient::JwtSigner,
+    dedupe::DedupeWindow,
+    intents::IntentStore,
+    jwtclient::{body_hash, JwtSigner},
+    telemetry,
     types::{
-        AssetResponse, CreateTransactionResponse, CreateVaultRequest, CreateVaultResponse,
-        DepositAddressResponse, TransactionArguments, TransactionDetails, VaultAccountResponse, VaultAccountPaginatedResponse,
+        AddressValidation, AssetResponse, CreateAddressRequest, CreateAddressResponse,
+        CreateTransactionResponse, CreateVaultAssetResponse, CreateVaultRequest,
+        CreateVaultResponse, DepositAddressResponse, DropTransactionRequest,
+        DropTransactionResponse, ErrorResponse, ExchangeAccount, ExchangeAsset,
+        ExchangeConvertRequest, ExchangeInternalTransferRequest, FreezeTransactionResponse,
+        GasStationConfiguration, GasStationInfo, NetworkFeeEstimate, OperationSuccessResponse,
+        RenameVaultRequest, SetAutoFuelRequest, SetCustomerRefIdRequest, SupportedAsset,
+        TransactionArguments, TransactionDetails, TransactionFilter, UpdateAddressRequest,
+        VaultAccountPaginatedResponse, VaultAccountResponse, VaultAssetSummary, WalletResponse,
     },
     FireblocksError, Result,
 };
 
+use futures_util::stream::{self, Stream};
 use jsonwebtoken::EncodingKey;
-use reqwest::{Client, RequestBuilder};
-use serde::{de::DeserializeOwned, Serialize};
+use reqwest::{header::CONTENT_TYPE, Client, RequestBuilder, StatusCode};
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Serialize,
+};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 
 const FIREBLOCKS_API: &str = "https://api.fireblocks.io";
+const FIREBLOCKS_SANDBOX_API: &str = "https://sandbox-api.fireblocks.io";
 const VERSION: &str = "v1";
+/// Environment variable which overrides the API version segment of request paths
+const VERSION_ENV: &str = "FIREBLOCKS_API_VERSION";
+/// Response header which holds the url of the next page of a listing
+const NEXT_PAGE: &str = "next-page";
+/// How many times a page of a listing is retried, and the delay before the first retry
+const PAGE_RETRIES: u32 = 5;
+const PAGE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The Fireblocks environment a client is pointed at. Used to label metrics and tag notes so
+/// that sandbox activity cannot be mistaken for production activity. It is set explicitly
+/// (see [`FireblocksClient::with_environment`]) rather than guessed from the API url.
+pub enum Environment {
+    #[default]
+    Production,
+    Sandbox,
+}
+
+impl Environment {
+    /// The API url of the environment
+    pub fn url(&self) -> &'static str {
+        match self {
+            Environment::Production => FIREBLOCKS_API,
+            Environment::Sandbox => FIREBLOCKS_SANDBOX_API,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Production => "production",
+            Environment::Sandbox => "sandbox",
+        }
+    }
+
+    /// Prefixes the note with the environment, unless we are in production. Notes which are
+    /// JSON documents (e.g. the preimages attached by `full_raw_note`) are left untouched so
+    /// that they still parse.
+    pub fn tag_note(&self, note: String) -> String {
+        match self {
+            Environment::Production => note,
+            _ if serde_json::from_str::<serde_json::Value>(&note).is_ok() => note,
+            _ => format!("[{}] {}", self.as_str(), note),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct FireblocksClient {
     pub signer: JwtSigner,
+    /// Credentials which are tried when the primary ones get rejected, e.g. while
+    /// an API key is being rotated.
+    pub secondary: Option<JwtSigner>,
+    environment: Environment,
+    pub(crate) dedupe: Option<DedupeWindow>,
+    /// Maps intent ids to the transactions submitted for them
+    pub intents: Option<Arc<dyn IntentStore>>,
+    #[cfg(feature = "journal")]
+    /// Write-ahead journal for submitted transactions
+    pub journal: Option<Arc<dyn crate::journal::Journal>>,
     client: Client,
     url: String,
     version: String,
@@ -32,68 +104,525 @@ impl FireblocksClient {
     pub fn new_with_url(key: EncodingKey, api_key: &str, url: &str) -> Self {
         Self {
             signer: JwtSigner::new(key, api_key),
+            secondary: None,
+            environment: Environment::default(),
+            dedupe: None,
+            intents: None,
+            #[cfg(feature = "journal")]
+            journal: None,
             client: Client::new(),
             url: url.to_owned(),
-            version: VERSION.to_owned(),
+            version: std::env::var(VERSION_ENV)
+                .map(|version| normalize_version(&version))
+                .unwrap_or_else(|_| VERSION.to_owned()),
         }
     }
 
+    /// Sets the version segment of the request paths, e.g. `v1`. Defaults to the
+    /// `FIREBLOCKS_API_VERSION` environment variable if set, or `v1` otherwise.
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = normalize_version(version);
+        self
+    }
+
+    /// The version segment of the request paths
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Sets the secondary credentials. Any request which gets rejected with a 401 while
+    /// using the primary credentials is transparently retried with these.
+    pub fn with_secondary(mut self, key: EncodingKey, api_key: &str) -> Self {
+        self.secondary = Some(JwtSigner::new(key, api_key));
+        self
+    }
+
+    /// Enables deduplication of submissions: creating the exact same transaction twice
+    /// within `window` returns the original transaction instead of a duplicate.
+    pub fn with_dedupe_window(mut self, window: std::time::Duration) -> Self {
+        self.dedupe = Some(DedupeWindow::new(window));
+        self
+    }
+
+    /// Sets the environment the client is pointed at, which labels its metrics and tags its
+    /// notes. Defaults to production, whatever the url.
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// The environment this client is pointed at
+    pub fn environment(&self) -> Environment {
+        self.environment
+    }
+
+    /// Submits a transaction. If an intent store is set and the transaction has an
+    /// `externalTxId` which was already submitted, the original transaction is returned
+    /// instead. If the store fails to record a new submission, the call fails with
+    /// [`FireblocksError::IntentNotStored`], which carries the id of the submitted transaction.
     pub async fn create_transaction(
         &self,
         tx: TransactionArguments,
     ) -> Result<CreateTransactionResponse> {
+        let intent = match (&self.intents, &tx.external_tx_id) {
+            (Some(intents), Some(intent_id)) => Some((intents, intent_id.clone())),
+            _ => None,
+        };
+        if let Some((intents, ref intent_id)) = intent {
+            if let Some(txid) = intents.get(intent_id).await? {
+                let details = self.transaction(&txid).await?;
+                return Ok(CreateTransactionResponse {
+                    id: details.id,
+                    status: details.status,
+                });
+            }
+        }
+
+        let res = self.create_transaction_once(tx).await?;
+        if let Some((intents, intent_id)) = intent {
+            if let Err(err) = intents.insert(&intent_id, &res.id).await {
+                return Err(FireblocksError::IntentNotStored {
+                    intent_id,
+                    tx_id: res.id,
+                    source: Box::new(err),
+                });
+            }
+        }
+        Ok(res)
+    }
+
+    /// Returns the transaction submitted for `intent_id`, if the intent store has one
+    pub async fn transaction_by_intent(
+        &self,
+        intent_id: &str,
+    ) -> Result<Option<TransactionDetails>> {
+        let txid = match self.intents {
+            Some(ref intents) => intents.get(intent_id).await?,
+            None => None,
+        };
+        match txid {
+            Some(txid) => Ok(Some(self.transaction(&txid).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn create_transaction_once(
+        &self,
+        tx: TransactionArguments,
+    ) -> Result<CreateTransactionResponse> {
+        match self.dedupe {
+            Some(ref dedupe) => {
+                let hash = body_hash(&tx).map_err(|err| FireblocksError::SerdeJson {
+                    err,
+                    text: "failed to hash transaction".to_owned(),
+                })?;
+                dedupe
+                    .get_or_submit(hash, || self.submit_transaction(tx))
+                    .await
+            }
+            None => self.submit_transaction(tx).await,
+        }
+    }
+
+    async fn submit_transaction(
+        &self,
+        tx: TransactionArguments,
+    ) -> Result<CreateTransactionResponse> {
+        #[cfg(feature = "journal")]
+        self.record("transactions", &tx)?;
         self.post("transactions", tx).await
     }
 
+    /// Streams every transaction matching `filter`, following the `next-page` cursors of the
+    /// listing so that arbitrarily long histories can be walked one page at a time. Pages
+    /// which fail with a [transient](FireblocksError::is_transient) error, e.g. because the
+    /// API is rate limiting, are retried with an exponential backoff. Any other error, or a
+    /// page which keeps failing, is yielded and ends the stream.
+    pub fn transactions_stream(
+        &self,
+        filter: TransactionFilter,
+    ) -> impl Stream<Item = Result<TransactionDetails>> + '_ {
+        let first = serde_urlencoded::to_string(&filter)
+            .map(|query| match query.is_empty() {
+                true => "transactions".to_owned(),
+                false => format!("transactions?{}", query),
+            })
+            .map_err(|err| FireblocksError::ParseError(err.to_string()));
+        let state = (VecDeque::new(), Some(first));
+        stream::unfold(state, move |(mut ready, mut next)| async move {
+            loop {
+                if let Some(tx) = ready.pop_front() {
+                    return Some((Ok(tx), (ready, next)));
+                }
+                let path = match next.take()? {
+                    Ok(path) => path,
+                    Err(err) => return Some((Err(err), (ready, None))),
+                };
+                match self.transactions_page(&path).await {
+                    Ok((page, cursor)) => {
+                        ready.extend(page);
+                        next = cursor.map(Ok);
+                    }
+                    Err(err) => return Some((Err(err), (ready, None))),
+                }
+            }
+        })
+    }
+
+    // Fetches a page of transactions, backing off while the API is rate limiting
+    async fn transactions_page(
+        &self,
+        path: &str,
+    ) -> Result<(Vec<TransactionDetails>, Option<String>)> {
+        let mut delay = PAGE_RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            match self.get_page(path).await {
+                Err(err) if err.is_transient() && attempt < PAGE_RETRIES => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                res => return res,
+            }
+        }
+    }
+
     pub async fn transaction(&self, txid: &str) -> Result<TransactionDetails> {
         self.get(&format!("transactions/{}", txid)).await
     }
+
+    /// Looks up the transaction which was submitted with `external_tx_id`, if any. Since
+    /// Fireblocks rejects duplicate `externalTxId`s, this tells whether a submission with that
+    /// idempotency key already exists, e.g. when recovering after a crash.
+    pub async fn transaction_by_external_id(
+        &self,
+        external_tx_id: &str,
+    ) -> Result<Option<TransactionDetails>> {
+        let path = format!(
+            "transactions/external_tx_id/{}",
+            path_segment(external_tx_id)?
+        );
+        match self.get(&path).await {
+            Ok(details) => Ok(Some(details)),
+            Err(FireblocksError::ApiError { status: 404, .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<TransactionDetails>> {
+        let query = serde_urlencoded::to_string(filter)
+            .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
+        if query.is_empty() {
+            self.get("transactions").await
+        } else {
+            self.get(&format!("transactions?{}", query)).await
+        }
+    }
+
+    /// Lists the assets supported by the workspace, with their decimals and contract addresses
+    pub async fn supported_assets(&self) -> Result<Vec<SupportedAsset>> {
+        self.get("supported_assets").await
+    }
+
+    /// Estimates the network fee of a transfer of `asset_id` at every fee level
+    pub async fn estimate_network_fee(&self, asset_id: &str) -> Result<NetworkFeeEstimate> {
+        let path = format!("estimate_network_fee?{}", query(&[("assetId", asset_id)])?);
+        self.get(&path).await
+    }
+
+    /// Validates `address` for `asset_id`, e.g. to pre-check a user-supplied withdrawal
+    /// address before submitting a transfer to it
+    pub async fn validate_address(
+        &self,
+        asset_id: &str,
+        address: &str,
+    ) -> Result<AddressValidation> {
+        self.get(&format!(
+            "transactions/validate_address/{}/{}",
+            path_segment(asset_id)?,
+            path_segment(address)?
+        ))
+        .await
+    }
+
+    pub async fn cancel_transaction(&self, txid: &str) -> Result<()> {
+        self.post_operation(&format!("transactions/{}/cancel", txid), ())
+            .await
+    }
+
+    /// Freezes an incoming transaction, holding its funds in the destination until it is
+    /// unfrozen
+    pub async fn freeze_transaction(&self, txid: &str) -> Result<FreezeTransactionResponse> {
+        self.freeze_operation(txid, "freeze").await
+    }
+
+    /// Releases a transaction which was frozen with [`freeze_transaction`](Self::freeze_transaction)
+    pub async fn unfreeze_transaction(&self, txid: &str) -> Result<FreezeTransactionResponse> {
+        self.freeze_operation(txid, "unfreeze").await
+    }
+
+    async fn freeze_operation(&self, txid: &str, op: &str) -> Result<FreezeTransactionResponse> {
+        let path = format!("transactions/{}/{}", txid, op);
+        let res: FreezeTransactionResponse = self.post(&path, ()).await?;
+        if !res.success {
+            return Err(FireblocksError::OperationFailed(path));
+        }
+        Ok(res)
+    }
+
+    /// Drops a stuck ETH transaction by replacing it with a 0-value transfer from the source to
+    /// itself, at the same nonce. The replacement is priced at `fee`'s fee level or gas price,
+    /// or at a fee picked by Fireblocks if neither is set.
+    pub async fn drop_transaction(
+        &self,
+        txid: &str,
+        fee: &DropTransactionRequest,
+    ) -> Result<DropTransactionResponse> {
+        let path = format!("transactions/{}/drop", txid);
+        let res: DropTransactionResponse = self.post(&path, fee).await?;
+        if !res.success {
+            return Err(FireblocksError::OperationFailed(path));
+        }
+        Ok(res)
+    }
 }
 
 // This impl block contains the underlying GET/POST helpers for authing to fireblocks
 impl FireblocksClient {
-    async fn get<R: DeserializeOwned>(&self, path: &str) -> Result<R> {
+    pub(crate) async fn get<R: DeserializeOwned>(&self, path: &str) -> Result<R> {
+        self.get_versioned(&self.version, path).await
+    }
+
+    // GETs a page of a listing, returning the path of the next page if there is one
+    async fn get_page<R: DeserializeOwned>(&self, path: &str) -> Result<(R, Option<String>)> {
         let path = format!("/{}/{}", self.version, path);
-        let req = self.client.get(&format!("{}{}", self.url, path));
-        self.send(&path, req, ()).await
+        let req = self.client.get(format!("{}{}", self.url, path));
+        self.send_paged(&path, req, &to_body(&())?).await
+    }
+
+    pub(crate) async fn post<S: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: S,
+    ) -> Result<R> {
+        self.post_versioned(&self.version, path, body).await
     }
 
-    async fn post<S: Serialize, R: DeserializeOwned>(&self, path: &str, body: S) -> Result<R> {
+    pub(crate) async fn put<S: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: S,
+    ) -> Result<R> {
         let path = format!("/{}/{}", self.version, path);
+        let body = to_body(&body)?;
         let req = self
             .client
-            .post(&format!("{}{}", self.url, path))
-            .json(&body);
-        self.send(&path, req, body).await
+            .put(format!("{}{}", self.url, path))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        self.send(&path, req, &body).await
     }
 
-    async fn send<S: Serialize, R: DeserializeOwned>(
+    // The versioned variants are for endpoints which live under a different prefix than
+    // the rest of the API
+    pub(crate) async fn get_versioned<R: DeserializeOwned>(
         &self,
+        version: &str,
+        path: &str,
+    ) -> Result<R> {
+        let path = format!("/{}/{}", version, path);
+        let req = self.client.get(format!("{}{}", self.url, path));
+        self.send(&path, req, &to_body(&())?).await
+    }
+
+    pub(crate) async fn post_versioned<S: Serialize, R: DeserializeOwned>(
+        &self,
+        version: &str,
         path: &str,
-        req: RequestBuilder,
         body: S,
     ) -> Result<R> {
-        let req = self.authed(path, req, body)?;
-        let res = req.send().await?;
+        let path = format!("/{}/{}", version, path);
+        // the JWT commits to the hash of the body, so make sure that we hash the exact
+        // bytes which are sent
+        let body = to_body(&body)?;
+        let req = self
+            .client
+            .post(format!("{}{}", self.url, path))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        self.send(&path, req, &body).await
+    }
+
+    async fn send<R: DeserializeOwned>(
+        &self,
+        path: &str,
+        req: RequestBuilder,
+        body: &[u8],
+    ) -> Result<R> {
+        Ok(self.send_paged(path, req, body).await?.0)
+    }
+
+    // Like `send`, but also returns the path of the next page if the response has a
+    // `next-page` header
+    async fn send_paged<R: DeserializeOwned>(
+        &self,
+        path: &str,
+        req: RequestBuilder,
+        body: &[u8],
+    ) -> Result<(R, Option<String>)> {
+        // only keep a copy of the request around if we have something to retry with
+        let retry = self.secondary.as_ref().and_then(|_| req.try_clone());
+        let res = self.authed(&self.signer, path, req, body)?.send().await?;
+        let res = match (res.status(), retry, &self.secondary) {
+            (StatusCode::UNAUTHORIZED, Some(req), Some(secondary)) => {
+                telemetry::secondary_key_used(self.environment);
+                self.authed(secondary, path, req, body)?.send().await?
+            }
+            _ => res,
+        };
+        let status = res.status();
+        telemetry::api_request(self.environment);
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            telemetry::rate_limited(self.environment);
+        }
+        // only reported once the status is known to be a success
+        let next_page = match res.headers().get(NEXT_PAGE) {
+            Some(url) => url
+                .to_str()
+                .map_err(|err| FireblocksError::ParseError(err.to_string()))
+                .and_then(|url| self.next_page_path(url)),
+            None => Ok(None),
+        };
         let text = res.text().await?;
+        if !status.is_success() {
+            telemetry::api_error(self.environment, status.as_u16());
+            return Err(match serde_json::from_str::<ErrorResponse>(&text) {
+                Ok(err) => FireblocksError::ApiError {
+                    status: status.as_u16(),
+                    code: err.code,
+                    message: err.message,
+                },
+                Err(_) => FireblocksError::ApiError {
+                    status: status.as_u16(),
+                    code: None,
+                    message: text,
+                },
+            });
+        }
         let res: R =
             serde_json::from_str(&text).map_err(|err| FireblocksError::SerdeJson { err, text })?;
-        Ok(res)
+        Ok((res, next_page?))
+    }
+
+    // The `next-page` header holds the full url of the next page, which is turned back
+    // into a path relative to the version segment. Its host is ignored, since it may not be
+    // the one the client talks to, e.g. behind a proxy. An empty header ends the listing.
+    fn next_page_path(&self, url: &str) -> Result<Option<String>> {
+        if url.is_empty() {
+            return Ok(None);
+        }
+        let invalid = || FireblocksError::ParseError(format!("invalid next page url: {}", url));
+        let parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+        let prefix = format!("/{}/", self.version);
+        let path = parsed.path();
+        let rest = path
+            .find(&prefix)
+            .map(|start| &path[start + prefix.len()..])
+            .filter(|rest| !rest.is_empty())
+            .ok_or_else(invalid)?;
+        // the cursor is opaque, so the query is decoded and re-encoded rather than trusted
+        match parsed.query() {
+            Some(params) => {
+                let params: Vec<(String, String)> =
+                    serde_urlencoded::from_str(params).map_err(|_| invalid())?;
+                Ok(Some(format!("{}?{}", rest, query(&params)?)))
+            }
+            None => Ok(Some(rest.to_owned())),
+        }
+    }
+
+    /// Records the body which is about to be posted to `path` in the journal, if any
+    #[cfg(feature = "journal")]
+    pub(crate) fn record<S: Serialize>(&self, path: &str, body: S) -> Result<()> {
+        if let Some(ref journal) = self.journal {
+            let path = format!("/{}/{}", self.version, path);
+            let entry = crate::journal::JournalEntry::new(&path, body).map_err(|err| {
+                FireblocksError::SerdeJson {
+                    err,
+                    text: "failed to serialize journal entry".to_owned(),
+                }
+            })?;
+            journal.record(&entry)?;
+        }
+        Ok(())
+    }
+
+    // Helper for management endpoints which respond with `{ "success": bool }`
+    async fn post_operation<S: Serialize>(&self, path: &str, body: S) -> Result<()> {
+        let res: OperationSuccessResponse = self.post(path, body).await?;
+        if !res.success {
+            return Err(FireblocksError::OperationFailed(path.to_owned()));
+        }
+        Ok(())
     }
 
     // Helper function which adds the necessary authorization headers to auth into the Fireblocks
     // API
-    fn authed<S: Serialize>(
+    fn authed(
         &self,
+        signer: &JwtSigner,
         url: &str,
         req: RequestBuilder,
-        body: S,
+        body: &[u8],
     ) -> Result<RequestBuilder> {
-        let jwt = self.signer.sign(url, body)?;
-        Ok(req
-            .header("X-API-Key", &self.signer.api_key)
-            .bearer_auth(jwt))
+        let jwt = signer.sign(url, body)?;
+        Ok(req.header("X-API-Key", &signer.api_key).bearer_auth(jwt))
+    }
+}
+
+/// Encodes query parameters
+fn query<S: Serialize>(params: &S) -> Result<String> {
+    serde_urlencoded::to_string(params).map_err(|err| FireblocksError::ParseError(err.to_string()))
+}
+
+/// Percent-encodes caller-supplied input for use as a single path segment, so that it cannot
+/// change which endpoint the (signed) path points at. `.` and `..` are rejected outright, since
+/// URLs resolve them even when encoded.
+pub(crate) fn path_segment(segment: &str) -> Result<String> {
+    if matches!(segment, "" | "." | "..") {
+        return Err(FireblocksError::ParseError(format!(
+            "invalid path segment {:?}",
+            segment
+        )));
+    }
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
     }
+    Ok(encoded)
+}
+
+fn normalize_version(version: &str) -> String {
+    version.trim_matches('/').to_owned()
+}
+
+/// Serializes a request body. Bodies are serialized exactly once and the resulting bytes are
+/// both hashed into the JWT and sent, so the two can never disagree. Struct fields are
+/// serialized in declaration order and maps in key order, so the output is stable.
+pub(crate) fn to_body<S: Serialize>(body: &S) -> Result<Vec<u8>> {
+    serde_json::to_vec(body).map_err(|err| FireblocksError::SerdeJson {
+        err,
+        text: "failed to serialize request body".to_owned(),
+    })
 }
 
 // This impl block contains the rest of "nice to have" endpoints
@@ -102,6 +631,36 @@ impl FireblocksClient {
         self.get("vault/accounts_paged").await
     }
 
+    /// Fetches all vault accounts, following the pagination cursors
+    pub async fn all_vaults(&self) -> Result<Vec<VaultAccountResponse>> {
+        let mut accounts = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let path = match after {
+                Some(ref cursor) => {
+                    format!("vault/accounts_paged?{}", query(&[("after", cursor)])?)
+                }
+                None => "vault/accounts_paged".to_owned(),
+            };
+            let page: VaultAccountPaginatedResponse = self.get(&path).await?;
+            accounts.extend(page.accounts);
+            match page.paging.after {
+                Some(cursor) if !cursor.is_empty() => after = Some(cursor),
+                _ => return Ok(accounts),
+            }
+        }
+    }
+
+    /// Returns the balances of every asset, aggregated across all vault accounts
+    pub async fn vault_assets(&self) -> Result<Vec<VaultAssetSummary>> {
+        self.get("vault/assets").await
+    }
+
+    /// Returns the balances of `asset_id`, aggregated across all vault accounts
+    pub async fn vault_asset(&self, asset_id: &str) -> Result<VaultAssetSummary> {
+        self.get(&format!("vault/assets/{}", asset_id)).await
+    }
+
     pub async fn vault(&self, account_id: &str) -> Result<VaultAccountResponse> {
         self.get(&format!("vault/accounts/{}", account_id)).await
     }
@@ -115,6 +674,233 @@ impl FireblocksClient {
         self.post("vault/accounts", req).await
     }
 
+    /// Renames the vault account `account_id`
+    pub async fn rename_vault(&self, account_id: &str, name: &str) -> Result<()> {
+        let req = RenameVaultRequest {
+            name: name.to_owned(),
+        };
+        let _: IgnoredAny = self
+            .put(&format!("vault/accounts/{}", account_id), req)
+            .await?;
+        Ok(())
+    }
+
+    /// Hides the vault account `account_id` from the console
+    pub async fn hide_vault(&self, account_id: &str) -> Result<()> {
+        self.post_operation(&format!("vault/accounts/{}/hide", account_id), ())
+            .await
+    }
+
+    /// Shows a vault account which was hidden with [`hide_vault`](Self::hide_vault) in the
+    /// console again
+    pub async fn unhide_vault(&self, account_id: &str) -> Result<()> {
+        self.post_operation(&format!("vault/accounts/{}/unhide", account_id), ())
+            .await
+    }
+
+    /// Sets the customer reference id of the vault account `account_id`, which attributes it
+    /// to a customer for AML purposes
+    pub async fn set_vault_customer_ref_id(&self, account_id: &str, ref_id: &str) -> Result<()> {
+        let req = SetCustomerRefIdRequest {
+            customer_ref_id: ref_id.to_owned(),
+        };
+        self.post_operation(
+            &format!("vault/accounts/{}/set_customer_ref_id", account_id),
+            req,
+        )
+        .await
+    }
+
+    /// Enables or disables auto fueling of the vault account `account_id` by the gas station
+    pub async fn set_auto_fuel(&self, account_id: &str, auto_fuel: bool) -> Result<()> {
+        self.post_operation(
+            &format!("vault/accounts/{}/set_auto_fuel", account_id),
+            SetAutoFuelRequest { auto_fuel },
+        )
+        .await
+    }
+
+    pub async fn new_vault_wallet(
+        &self,
+        account_id: &str,
+        asset_id: &str,
+    ) -> Result<CreateVaultAssetResponse> {
+        self.post(&format!("vault/accounts/{}/{}", account_id, asset_id), ())
+            .await
+    }
+
+    /// Activates the `asset_id` wallet of the vault account `account_id`, for assets which
+    /// must be activated before they can receive deposits
+    pub async fn activate_wallet(
+        &self,
+        account_id: &str,
+        asset_id: &str,
+    ) -> Result<CreateVaultAssetResponse> {
+        self.post(
+            &format!("vault/accounts/{}/{}/activate", account_id, asset_id),
+            (),
+        )
+        .await
+    }
+
+    /// The balances and auto-fueling configuration of the gas station
+    pub async fn gas_station(&self) -> Result<GasStationInfo> {
+        self.get("gas_station").await
+    }
+
+    /// The balance and auto-fueling configuration of the gas station for `asset_id`
+    pub async fn gas_station_asset(&self, asset_id: &str) -> Result<GasStationInfo> {
+        self.get(&format!("gas_station/{}", asset_id)).await
+    }
+
+    /// Updates the auto-fueling thresholds of the gas station
+    pub async fn set_gas_station_configuration(
+        &self,
+        config: &GasStationConfiguration,
+    ) -> Result<()> {
+        let _: IgnoredAny = self.put("gas_station/configuration", config).await?;
+        Ok(())
+    }
+
+    pub async fn internal_wallets(&self) -> Result<Vec<WalletResponse>> {
+        self.get("internal_wallets").await
+    }
+
+    pub async fn internal_wallet(&self, wallet_id: &str) -> Result<WalletResponse> {
+        self.get(&format!("internal_wallets/{}", wallet_id)).await
+    }
+
+    pub async fn external_wallets(&self) -> Result<Vec<WalletResponse>> {
+        self.get("external_wallets").await
+    }
+
+    pub async fn external_wallet(&self, wallet_id: &str) -> Result<WalletResponse> {
+        self.get(&format!("external_wallets/{}", wallet_id)).await
+    }
+
+    pub async fn exchange_accounts(&self) -> Result<Vec<ExchangeAccount>> {
+        self.get("exchange_accounts").await
+    }
+
+    pub async fn exchange_account(&self, exchange_account_id: &str) -> Result<ExchangeAccount> {
+        self.get(&format!("exchange_accounts/{}", exchange_account_id))
+            .await
+    }
+
+    /// The balance of `asset_id` in an exchange account
+    pub async fn exchange_asset(
+        &self,
+        exchange_account_id: &str,
+        asset_id: &str,
+    ) -> Result<ExchangeAsset> {
+        self.get(&format!(
+            "exchange_accounts/{}/{}",
+            exchange_account_id, asset_id
+        ))
+        .await
+    }
+
+    /// Moves funds between the main account of an exchange and its sub / trading accounts
+    pub async fn exchange_internal_transfer(
+        &self,
+        exchange_account_id: &str,
+        req: &ExchangeInternalTransferRequest,
+    ) -> Result<()> {
+        self.post_operation(
+            &format!(
+                "exchange_accounts/{}/internal_transfer",
+                exchange_account_id
+            ),
+            req,
+        )
+        .await
+    }
+
+    /// Converts between two assets held in an exchange account, e.g. USD and a stablecoin
+    pub async fn exchange_convert(
+        &self,
+        exchange_account_id: &str,
+        req: &ExchangeConvertRequest,
+    ) -> Result<()> {
+        self.post_operation(
+            &format!("exchange_accounts/{}/convert", exchange_account_id),
+            req,
+        )
+        .await
+    }
+
+    /// The whitelisted contract wallets of the workspace
+    pub async fn contracts(&self) -> Result<Vec<WalletResponse>> {
+        self.get("contracts").await
+    }
+
+    pub async fn contract(&self, contract_id: &str) -> Result<WalletResponse> {
+        self.get(&format!("contracts/{}", contract_id)).await
+    }
+
+    /// Creates an additional deposit address for the `asset_id` wallet of the vault account
+    /// `account_id`, e.g. to issue a deposit address per customer
+    pub async fn create_vault_address(
+        &self,
+        account_id: &str,
+        asset_id: &str,
+        description: Option<&str>,
+        customer_ref_id: Option<&str>,
+    ) -> Result<CreateAddressResponse> {
+        let req = CreateAddressRequest {
+            description: description.map(ToOwned::to_owned),
+            customer_ref_id: customer_ref_id.map(ToOwned::to_owned),
+        };
+        self.post(
+            &format!("vault/accounts/{}/{}/addresses", account_id, asset_id),
+            req,
+        )
+        .await
+    }
+
+    /// Sets the description of the deposit address `address` (formatted as `address:tag` for
+    /// assets with tags) of a vault wallet
+    pub async fn update_vault_address(
+        &self,
+        account_id: &str,
+        asset_id: &str,
+        address: &str,
+        description: &str,
+    ) -> Result<()> {
+        let req = UpdateAddressRequest {
+            description: description.to_owned(),
+        };
+        let path = format!(
+            "vault/accounts/{}/{}/addresses/{}",
+            account_id,
+            asset_id,
+            path_segment(address)?
+        );
+        let _: IgnoredAny = self.put(&path, req).await?;
+        Ok(())
+    }
+
+    /// Sets the customer reference id of the deposit address `address` (formatted as
+    /// `address:tag` for assets with tags) of a vault wallet
+    pub async fn set_vault_address_customer_ref_id(
+        &self,
+        account_id: &str,
+        asset_id: &str,
+        address: &str,
+        ref_id: &str,
+    ) -> Result<()> {
+        let req = SetCustomerRefIdRequest {
+            customer_ref_id: ref_id.to_owned(),
+        };
+        let path = format!(
+            "vault/accounts/{}/{}/addresses/{}/set_customer_ref_id",
+            account_id,
+            asset_id,
+            path_segment(address)?
+        );
+        self.post_operation(&path, req).await
+    }
+
     pub async fn vault_addresses(
         &self,
         account_id: &str,
@@ -156,4 +942,73 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[test]
+    fn overrides_version() {
+        let client = FireblocksClient::new(EncodingKey::from_secret(b"secret"), "key");
+        assert_eq!(client.with_version("/v2/").version(), "v2");
+    }
+
+    #[test]
+    fn tags_notes_with_the_environment() {
+        let key = || EncodingKey::from_secret(b"");
+        // the url alone does not make a client a sandbox one
+        let client = FireblocksClient::new_with_url(key(), "key", "https://sandbox.proxy.local");
+        assert_eq!(client.environment(), Environment::Production);
+        let client = FireblocksClient::new(key(), "key").with_environment(Environment::Sandbox);
+        assert_eq!(client.environment(), Environment::Sandbox);
+
+        let sandbox = Environment::Sandbox;
+        assert_eq!(
+            sandbox.tag_note("Transfer".to_owned()),
+            "[sandbox] Transfer"
+        );
+        assert_eq!(
+            Environment::Production.tag_note("Transfer".to_owned()),
+            "Transfer"
+        );
+        let preimage = r#"{"to":"0x0000000000000000000000000000000000000002"}"#;
+        assert_eq!(sandbox.tag_note(preimage.to_owned()), preimage);
+    }
+
+    #[test]
+    fn encodes_path_segments() {
+        assert_eq!(
+            path_segment("0xcBE74E21B070A979b9d6426b11e876d4cB618DaF").unwrap(),
+            "0xcBE74E21B070A979b9d6426b11e876d4cB618DaF"
+        );
+        assert_eq!(
+            path_segment("../vault/accounts?x=1").unwrap(),
+            "..%2Fvault%2Faccounts%3Fx%3D1"
+        );
+        assert_eq!(path_segment("rAddr:1234").unwrap(), "rAddr%3A1234");
+        assert!(path_segment("..").is_err());
+        assert!(path_segment(".").is_err());
+        assert!(path_segment("").is_err());
+    }
+
+    #[test]
+    fn follows_next_page_urls() {
+        let client = FireblocksClient::new(EncodingKey::from_secret(b"secret"), "key");
+        let next = |url| client.next_page_path(url).unwrap();
+        assert_eq!(
+            next("https://api.fireblocks.io/v1/transactions?next=abc&limit=500").as_deref(),
+            Some("transactions?next=abc&limit=500")
+        );
+        assert_eq!(
+            next("https://api.fireblocks.io/v1/transactions?next=ab/c d==").as_deref(),
+            Some("transactions?next=ab%2Fc+d%3D%3D")
+        );
+        // the host is ignored, e.g. when the client goes through a proxy
+        assert_eq!(
+            next("https://proxy.local/fireblocks/v1/transactions?next=abc").as_deref(),
+            Some("transactions?next=abc")
+        );
+        // an empty header ends the listing, an unusable one is an error
+        assert_eq!(next(""), None);
+        assert!(client.next_page_path("not a url").is_err());
+        assert!(client
+            .next_page_path("https://api.fireblocks.io/transactions?next=abc")
+            .is_err());
+    }
 }
@@ -0,0 +1,199 @@
+//! Cache of the workspace's vault account metadata with change detection.
+use crate::{api::FireblocksClient, types::VaultAccountResponse, FireblocksSigner, Result};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A change to a vault account's metadata which was detected on refresh
+pub enum VaultChange {
+    Added(String),
+    Removed(String),
+    Renamed {
+        id: String,
+        old: String,
+        new: String,
+    },
+    CustomerRefIdChanged {
+        id: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    AssetsChanged {
+        id: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Keeps a copy of all vault accounts and reports what changed on every refresh, so that
+/// long-running services can react to changes made in the console without full rescans.
+pub struct VaultCache {
+    fireblocks: FireblocksClient,
+    accounts: BTreeMap<String, VaultAccountResponse>,
+}
+
+impl VaultCache {
+    /// Creates an empty cache using the signer's credentials. The first
+    /// [`refresh`](Self::refresh) reports every vault account as added.
+    pub fn new(signer: &FireblocksSigner) -> Self {
+        Self {
+            fireblocks: signer.fireblocks.clone(),
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the cached vault account
+    pub fn get(&self, account_id: &str) -> Option<&VaultAccountResponse> {
+        self.accounts.get(account_id)
+    }
+
+    /// Returns all the cached vault accounts, ordered by id
+    pub fn accounts(&self) -> impl Iterator<Item = &VaultAccountResponse> {
+        self.accounts.values()
+    }
+
+    /// Re-fetches all vault accounts and returns the changes since the last refresh
+    pub async fn refresh(&mut self) -> Result<Vec<VaultChange>> {
+        let accounts = self
+            .fireblocks
+            .all_vaults()
+            .await?
+            .into_iter()
+            .map(|account| (account.id.clone(), account))
+            .collect();
+        Ok(self.update(accounts))
+    }
+
+    /// Refreshes the cache every `interval`, sending the detected changes to `changes` until
+    /// the receiving end is dropped or a refresh fails.
+    pub async fn run(
+        mut self,
+        interval: Duration,
+        changes: UnboundedSender<VaultChange>,
+    ) -> Result<()> {
+        loop {
+            for change in self.refresh().await? {
+                if changes.send(change).is_err() {
+                    return Ok(());
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn update(&mut self, accounts: BTreeMap<String, VaultAccountResponse>) -> Vec<VaultChange> {
+        let mut changes = Vec::new();
+        for id in self.accounts.keys() {
+            if !accounts.contains_key(id) {
+                changes.push(VaultChange::Removed(id.clone()));
+            }
+        }
+        for (id, new) in &accounts {
+            let old = match self.accounts.get(id) {
+                Some(old) => old,
+                None => {
+                    changes.push(VaultChange::Added(id.clone()));
+                    continue;
+                }
+            };
+            if old.name != new.name {
+                changes.push(VaultChange::Renamed {
+                    id: id.clone(),
+                    old: old.name.clone(),
+                    new: new.name.clone(),
+                });
+            }
+            if old.customer_ref_id != new.customer_ref_id {
+                changes.push(VaultChange::CustomerRefIdChanged {
+                    id: id.clone(),
+                    old: old.customer_ref_id.clone(),
+                    new: new.customer_ref_id.clone(),
+                });
+            }
+            let missing_from = |a: &VaultAccountResponse, b: &VaultAccountResponse| {
+                a.assets
+                    .iter()
+                    .filter(|asset| !b.assets.iter().any(|other| other.id == asset.id))
+                    .map(|asset| asset.id.clone())
+                    .collect::<Vec<_>>()
+            };
+            let (added, removed) = (missing_from(new, old), missing_from(old, new));
+            if !added.is_empty() || !removed.is_empty() {
+                changes.push(VaultChange::AssetsChanged {
+                    id: id.clone(),
+                    added,
+                    removed,
+                });
+            }
+        }
+        self.accounts = accounts;
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::EncodingKey;
+
+    fn account(id: &str, name: &str, assets: &[&str]) -> (String, VaultAccountResponse) {
+        let account = serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "hiddenOnUI": false,
+            "autoFuel": false,
+            "assets": assets
+                .iter()
+                .map(|asset| serde_json::json!({ "id": asset, "total": "0" }))
+                .collect::<Vec<_>>(),
+        }))
+        .unwrap();
+        (id.to_owned(), account)
+    }
+
+    #[test]
+    fn detects_changes() {
+        let mut cache = VaultCache {
+            fireblocks: FireblocksClient::new(EncodingKey::from_secret(b"secret"), "key"),
+            accounts: BTreeMap::new(),
+        };
+        let changes = cache.update(
+            vec![
+                account("0", "treasury", &["ETH"]),
+                account("1", "payouts", &[]),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            changes,
+            vec![
+                VaultChange::Added("0".to_owned()),
+                VaultChange::Added("1".to_owned())
+            ]
+        );
+
+        let changes = cache.update(
+            vec![account("0", "cold treasury", &["ETH_TEST3"])]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            changes,
+            vec![
+                VaultChange::Removed("1".to_owned()),
+                VaultChange::Renamed {
+                    id: "0".to_owned(),
+                    old: "treasury".to_owned(),
+                    new: "cold treasury".to_owned(),
+                },
+                VaultChange::AssetsChanged {
+                    id: "0".to_owned(),
+                    added: vec!["ETH_TEST3".to_owned()],
+                    removed: vec!["ETH".to_owned()],
+                },
+            ]
+        );
+    }
+}
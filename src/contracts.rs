@@ -0,0 +1,268 @@
+//! Wrappers for the [contract interactions](https://developers.fireblocks.com/reference/readcallfunction)
+//! endpoints.
+//!
+//! Instead of submitting locally ABI-encoded `CONTRACT_CALL` data, the function and its
+//! arguments are sent to Fireblocks, which encodes them. This lets TAP policies and approvers
+//! see the function names and arguments of the calls they are approving.
+#[cfg(feature = "ethers")]
+use crate::FireblocksError;
+use crate::{api::FireblocksClient, Result};
+#[cfg(feature = "ethers")]
+use ethers_core::{
+    abi::{Function, Param, ParamType, StateMutability, Token},
+    utils::{hex, to_checksum},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A function of a contract's ABI, optionally with the values of its inputs
+pub struct AbiFunction {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<AbiParameter>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<AbiParameter>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_mutability: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// An input or output of an [`AbiFunction`]
+pub struct AbiParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<AbiParameter>>,
+    /// The value of the parameter, set on the inputs of calls and the outputs of reads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+#[cfg(feature = "ethers")]
+impl AbiFunction {
+    /// Describes a call to `function` with the provided arguments
+    pub fn call(function: &Function, args: &[Token]) -> Result<Self> {
+        if function.inputs.len() != args.len() {
+            return Err(FireblocksError::ParseError(format!(
+                "{} expects {} arguments, got {}",
+                function.name,
+                function.inputs.len(),
+                args.len()
+            )));
+        }
+        let inputs = function
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| AbiParameter {
+                value: Some(token_value(arg)),
+                ..AbiParameter::from(param)
+            })
+            .collect();
+        Ok(Self {
+            kind: "function".to_owned(),
+            name: Some(function.name.clone()),
+            inputs,
+            outputs: Some(function.outputs.iter().map(AbiParameter::from).collect()),
+            state_mutability: Some(state_mutability(function.state_mutability).to_owned()),
+        })
+    }
+}
+
+#[cfg(feature = "ethers")]
+impl From<&Param> for AbiParameter {
+    fn from(param: &Param) -> Self {
+        let mut res = parameter(param.name.clone(), &param.kind);
+        res.internal_type = param.internal_type.clone();
+        res
+    }
+}
+
+#[cfg(feature = "ethers")]
+fn parameter(name: String, kind: &ParamType) -> AbiParameter {
+    // tuples are described by their components rather than by their signature
+    let (kind, components) = match kind {
+        ParamType::Tuple(members) => ("tuple".to_owned(), Some(members)),
+        ParamType::Array(inner) => match &**inner {
+            ParamType::Tuple(members) => ("tuple[]".to_owned(), Some(members)),
+            _ => (kind.to_string(), None),
+        },
+        ParamType::FixedArray(inner, len) => match &**inner {
+            ParamType::Tuple(members) => (format!("tuple[{}]", len), Some(members)),
+            _ => (kind.to_string(), None),
+        },
+        _ => (kind.to_string(), None),
+    };
+    AbiParameter {
+        name,
+        kind,
+        internal_type: None,
+        components: components.map(|members| {
+            members
+                .iter()
+                .enumerate()
+                .map(|(i, member)| parameter(i.to_string(), member))
+                .collect()
+        }),
+        value: None,
+    }
+}
+
+#[cfg(feature = "ethers")]
+fn state_mutability(mutability: StateMutability) -> &'static str {
+    match mutability {
+        StateMutability::Pure => "pure",
+        StateMutability::View => "view",
+        StateMutability::NonPayable => "nonpayable",
+        StateMutability::Payable => "payable",
+    }
+}
+
+#[cfg(feature = "ethers")]
+// Fireblocks expects integers as decimal strings and bytes as 0x-prefixed hex
+fn token_value(token: &Token) -> Value {
+    match token {
+        Token::Address(address) => Value::String(to_checksum(address, None)),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => {
+            Value::String(format!("0x{}", hex::encode(bytes)))
+        }
+        Token::Int(int) => Value::String(ethers_core::types::I256::from_raw(*int).to_string()),
+        Token::Uint(uint) => Value::String(uint.to_string()),
+        Token::Bool(b) => Value::Bool(*b),
+        Token::String(s) => Value::String(s.clone()),
+        Token::FixedArray(tokens) | Token::Array(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.iter().map(token_value).collect())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The ABI which Fireblocks has on record for a contract
+pub struct ContractAbiResponse {
+    pub contract_address: String,
+    pub base_asset_id: String,
+    pub abi: Vec<AbiFunction>,
+    /// The ABI of the implementation, if the contract is a proxy
+    #[serde(default)]
+    pub implementation_abi: Option<Vec<AbiFunction>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadCallRequest {
+    pub abi_function: AbiFunction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteCallRequest {
+    pub vault_account_id: String,
+    pub abi_function: AbiFunction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The amount of the base asset sent along with the call
+    pub amount: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteCallResponse {
+    pub tx_id: String,
+}
+
+impl FireblocksClient {
+    /// Fetches the ABI of the contract deployed at `address` on `base_asset_id`
+    pub async fn contract_abi(
+        &self,
+        base_asset_id: &str,
+        address: &str,
+    ) -> Result<ContractAbiResponse> {
+        self.get(&contract_path(base_asset_id, address, "functions"))
+            .await
+    }
+
+    /// Calls a read-only function, returning its outputs
+    pub async fn read_contract(
+        &self,
+        base_asset_id: &str,
+        address: &str,
+        function: AbiFunction,
+    ) -> Result<Vec<AbiParameter>> {
+        let req = ReadCallRequest {
+            abi_function: function,
+        };
+        self.post(
+            &contract_path(base_asset_id, address, "functions/read"),
+            req,
+        )
+        .await
+    }
+
+    /// Submits a transaction calling a state-changing function, returning its Fireblocks
+    /// transaction id
+    pub async fn write_contract(
+        &self,
+        base_asset_id: &str,
+        address: &str,
+        req: WriteCallRequest,
+    ) -> Result<WriteCallResponse> {
+        self.post(
+            &contract_path(base_asset_id, address, "functions/write"),
+            req,
+        )
+        .await
+    }
+}
+
+fn contract_path(base_asset_id: &str, address: &str, endpoint: &str) -> String {
+    format!(
+        "contract_interactions/base_asset_id/{}/contract_address/{}/{}",
+        base_asset_id, address, endpoint
+    )
+}
+
+#[cfg(all(test, feature = "ethers"))]
+mod tests {
+    use super::*;
+    use ethers_core::{abi::AbiParser, types::Address};
+
+    #[test]
+    fn describes_calls() {
+        let function = AbiParser::default()
+            .parse_function("function transfer(address to, uint256 amount) returns (bool)")
+            .unwrap();
+        let to: Address = "0xcBE74E21B070A979b9d6426b11e876d4cB618DaF"
+            .parse()
+            .unwrap();
+        let call =
+            AbiFunction::call(&function, &[Token::Address(to), Token::Uint(100.into())]).unwrap();
+        assert_eq!(
+            serde_json::to_value(&call).unwrap(),
+            serde_json::json!({
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    { "name": "to", "type": "address", "value": "0xcBE74E21B070A979b9d6426b11e876d4cB618DaF" },
+                    { "name": "amount", "type": "uint256", "value": "100" }
+                ],
+                "outputs": [{ "name": "", "type": "bool" }],
+                "stateMutability": "nonpayable"
+            })
+        );
+
+        assert!(AbiFunction::call(&function, &[Token::Address(to)]).is_err());
+    }
+}
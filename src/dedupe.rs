@@ -0,0 +1,81 @@
+//! In-process deduplication of transaction submissions.
+use crate::{types::CreateTransactionResponse, Result};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+
+type Slot = Arc<OnceCell<CreateTransactionResponse>>;
+
+#[derive(Debug, Clone)]
+/// Remembers the response of every submission for `window`, keyed by the hash of its body,
+/// so that submitting the exact same body twice within the window returns the original
+/// response instead of creating a duplicate transaction. Concurrent submissions of the same
+/// body wait for the first one to complete. Clones share the same window.
+pub(crate) struct DedupeWindow {
+    window: Duration,
+    entries: Arc<Mutex<HashMap<String, (Instant, Slot)>>>,
+}
+
+impl DedupeWindow {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns the response stored under `body_hash`, or runs `submit` to create it. Failed
+    /// submissions are not remembered.
+    pub async fn get_or_submit<F, Fut>(
+        &self,
+        body_hash: String,
+        submit: F,
+    ) -> Result<CreateTransactionResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<CreateTransactionResponse>>,
+    {
+        let slot = {
+            let mut entries = self.entries.lock().expect("dedupe lock poisoned");
+            let now = Instant::now();
+            entries.retain(|_, (created, _)| now.duration_since(*created) < self.window);
+            entries
+                .entry(body_hash)
+                .or_insert_with(|| (now, Default::default()))
+                .1
+                .clone()
+        };
+        slot.get_or_try_init(submit).await.cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TransactionStatus;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn dedupes_within_window() {
+        let dedupe = DedupeWindow::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let submit = || async {
+            let id = calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CreateTransactionResponse {
+                id: id.to_string(),
+                status: TransactionStatus::SUBMITTED,
+            })
+        };
+
+        let first = dedupe.get_or_submit("a".to_owned(), submit).await.unwrap();
+        let second = dedupe.get_or_submit("a".to_owned(), submit).await.unwrap();
+        let other = dedupe.get_or_submit("b".to_owned(), submit).await.unwrap();
+        assert_eq!(first.id, second.id);
+        assert_ne!(first.id, other.id);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
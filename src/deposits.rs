@@ -0,0 +1,355 @@
+//! Monitoring of the deposits made into a vault account.
+//!
+//! [`DepositMonitor`] polls the transactions whose destination is the vault and, optionally,
+//! consumes the transaction [webhook events](crate::webhook) for lower latency. Both sources
+//! are merged into a single stream of [`IncomingDeposit`]s, which yields a deposit every time
+//! its status or number of confirmations changes, until it reaches a final status.
+use crate::{
+    api::FireblocksClient,
+    types::{
+        PeerType, TransactionDetails, TransactionFilter, TransactionStatus,
+        TransferPeerPathResponse,
+    },
+    webhook::WebhookEvent,
+    Result,
+};
+use futures_util::stream::{self, Stream, TryStreamExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::{sleep_until, Instant},
+};
+
+#[derive(Debug, Clone)]
+/// A transfer into the monitored vault account
+pub struct IncomingDeposit {
+    pub tx_id: String,
+    pub asset_id: String,
+    pub tx_hash: String,
+    pub status: TransactionStatus,
+    pub source: Option<TransferPeerPathResponse>,
+    pub source_address: Option<String>,
+    /// The amount, denominated in the asset's units
+    pub amount: Option<String>,
+    pub confirmations: u64,
+    /// Whether the deposit can be credited, i.e. it COMPLETED or it has reached the
+    /// required number of confirmations
+    pub confirmed: bool,
+}
+
+#[derive(Debug, Clone)]
+/// Feeds webhook events into a [`DepositMonitor`]'s stream
+pub struct DepositEvents {
+    vault_id: String,
+    sender: UnboundedSender<TransactionDetails>,
+}
+
+impl DepositEvents {
+    /// Forwards the event to the stream if it is a transaction event for a deposit into the
+    /// monitored vault. Returns whether the event was forwarded.
+    pub fn handle(&self, event: &WebhookEvent) -> bool {
+        if !event.event_type.starts_with("TRANSACTION_") {
+            return false;
+        }
+        match serde_json::from_value::<TransactionDetails>(event.data.clone()) {
+            Ok(tx) if is_deposit_into(&tx, &self.vault_id) => self.sender.send(tx).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DepositMonitor {
+    fireblocks: FireblocksClient,
+    vault_id: String,
+    required_confirmations: Option<u64>,
+    poll_interval: Duration,
+    since: Option<u64>,
+    sender: UnboundedSender<TransactionDetails>,
+    receiver: UnboundedReceiver<TransactionDetails>,
+}
+
+impl DepositMonitor {
+    /// Monitors the deposits into `vault_id`, polling every 30 seconds
+    pub fn new(fireblocks: FireblocksClient, vault_id: &str) -> Self {
+        let (sender, receiver) = unbounded_channel();
+        Self {
+            fireblocks,
+            vault_id: vault_id.to_owned(),
+            required_confirmations: None,
+            poll_interval: Duration::from_secs(30),
+            since: None,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Considers deposits confirmed once they have `confirmations` confirmations, instead of
+    /// waiting for them to COMPLETE
+    pub fn confirmations(mut self, confirmations: u64) -> Self {
+        self.required_confirmations = Some(confirmations);
+        self
+    }
+
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Only reports deposits created after `timestamp` (milliseconds since Epoch). Defaults
+    /// to the time at which the stream is created.
+    pub fn since(mut self, timestamp: u64) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    /// Returns a handle which forwards webhook events into the stream
+    pub fn events(&self) -> DepositEvents {
+        DepositEvents {
+            vault_id: self.vault_id.clone(),
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Consumes the monitor into the stream of deposits. Polling errors are yielded without
+    /// ending the stream.
+    pub fn stream(self) -> impl Stream<Item = Result<IncomingDeposit>> {
+        let since = self.since.unwrap_or_else(now_millis);
+        let state = State {
+            tracker: Tracker::new(self.vault_id.clone(), self.required_confirmations, since),
+            monitor: self,
+            next_poll: Instant::now(),
+            ready: VecDeque::new(),
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(deposit) = state.ready.pop_front() {
+                    return Some((Ok(deposit), state));
+                }
+                tokio::select! {
+                    _ = sleep_until(state.next_poll) => {
+                        state.next_poll = Instant::now() + state.monitor.poll_interval;
+                        if let Err(err) = state.poll().await {
+                            return Some((Err(err), state));
+                        }
+                    }
+                    Some(tx) = state.monitor.receiver.recv() => {
+                        state.ready.extend(state.tracker.observe(tx));
+                    }
+                }
+            }
+        })
+    }
+}
+
+struct State {
+    monitor: DepositMonitor,
+    tracker: Tracker,
+    next_poll: Instant,
+    ready: VecDeque<IncomingDeposit>,
+}
+
+impl State {
+    async fn poll(&mut self) -> Result<()> {
+        let filter = TransactionFilter {
+            // the cursor itself must be included, so that unsettled deposits are re-fetched
+            after: Some(self.tracker.cursor().saturating_sub(1)),
+            dest_type: Some(PeerType::VAULT_ACCOUNT),
+            dest_id: Some(self.monitor.vault_id.clone()),
+            ..Default::default()
+        };
+        // every page is fetched before any deposit is observed, since observing a page moves
+        // the cursor past the older pages
+        let txs: Vec<_> = self
+            .monitor
+            .fireblocks
+            .transactions_stream(filter)
+            .try_collect()
+            .await?;
+        for tx in txs {
+            self.ready.extend(self.tracker.observe(tx));
+        }
+        Ok(())
+    }
+}
+
+// Remembers the last reported state of every deposit, so that each change is reported once
+// regardless of whether it was polled or received through a webhook.
+struct Tracker {
+    vault_id: String,
+    required_confirmations: Option<u64>,
+    since: u64,
+    // tx id => (created at, status, confirmations)
+    seen: HashMap<String, (u64, TransactionStatus, u64)>,
+}
+
+impl Tracker {
+    fn new(vault_id: String, required_confirmations: Option<u64>, since: u64) -> Self {
+        Self {
+            vault_id,
+            required_confirmations,
+            since,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// The creation time of the oldest unsettled deposit, or of the newest one if all are
+    /// settled. Deposits settled before the cursor are forgotten, as they won't be polled.
+    fn cursor(&mut self) -> u64 {
+        let unsettled = self
+            .seen
+            .values()
+            .filter(|(_, status, _)| !status.is_terminal())
+            .map(|(created_at, _, _)| *created_at)
+            .min();
+        let cursor = unsettled
+            .or_else(|| {
+                self.seen
+                    .values()
+                    .map(|(created_at, _, _)| *created_at)
+                    .max()
+            })
+            .unwrap_or(self.since)
+            .max(self.since);
+        self.seen
+            .retain(|_, (created_at, _, _)| *created_at >= cursor);
+        cursor
+    }
+
+    fn observe(&mut self, tx: TransactionDetails) -> Option<IncomingDeposit> {
+        if !is_deposit_into(&tx, &self.vault_id) {
+            return None;
+        }
+        let created_at = tx.created_at.unwrap_or(self.since);
+        if created_at < self.since {
+            return None;
+        }
+        let confirmations = tx.num_of_confirmations.unwrap_or_default();
+        match self.seen.get(&tx.id) {
+            // a webhook may arrive after a more recent poll, never go backwards
+            Some((_, status, seen_confirmations))
+                if status.is_terminal()
+                    || (*status == tx.status && *seen_confirmations >= confirmations) =>
+            {
+                return None
+            }
+            _ => {}
+        }
+        self.seen
+            .insert(tx.id.clone(), (created_at, tx.status, confirmations));
+
+        let confirmed = tx.status == TransactionStatus::COMPLETED
+            || (!tx.status.is_terminal()
+                && self
+                    .required_confirmations
+                    .is_some_and(|required| confirmations >= required));
+        Some(IncomingDeposit {
+            tx_id: tx.id,
+            asset_id: tx.asset_id,
+            tx_hash: tx.tx_hash,
+            status: tx.status,
+            source: tx.source,
+            source_address: tx.source_address,
+            amount: tx.amount_info.and_then(|info| info.amount),
+            confirmations,
+            confirmed,
+        })
+    }
+}
+
+fn is_deposit_into(tx: &TransactionDetails, vault_id: &str) -> bool {
+    matches!(
+        tx.destination,
+        Some(TransferPeerPathResponse {
+            peer_type: PeerType::VAULT_ACCOUNT,
+            id: Some(ref id),
+            ..
+        }) if id == vault_id
+    )
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_api::{MockApi, MockResponse};
+    use futures_util::StreamExt;
+
+    fn deposit(id: &str, status: &str, confirmations: u64) -> TransactionDetails {
+        serde_json::from_value(deposit_json(id, status, confirmations)).unwrap()
+    }
+
+    fn deposit_json(id: &str, status: &str, confirmations: u64) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "assetId": "ETH",
+            "txHash": "0xabc",
+            "status": status,
+            "subStatus": "",
+            "signedMessages": [],
+            "createdAt": 1_000,
+            "source": { "type": "UNKNOWN" },
+            "destination": { "type": "VAULT_ACCOUNT", "id": "1" },
+            "amountInfo": { "amount": "0.5" },
+            "numOfConfirmations": confirmations
+        })
+    }
+
+    #[test]
+    fn tracks_confirmations() {
+        let mut tracker = Tracker::new("1".to_owned(), Some(3), 0);
+
+        let first = tracker.observe(deposit("a", "CONFIRMING", 1)).unwrap();
+        assert!(!first.confirmed);
+        assert_eq!(first.amount.as_deref(), Some("0.5"));
+        // the same state received through a webhook and a poll is reported once
+        assert!(tracker.observe(deposit("a", "CONFIRMING", 1)).is_none());
+
+        let confirmed = tracker.observe(deposit("a", "CONFIRMING", 3)).unwrap();
+        assert!(confirmed.confirmed);
+        assert!(tracker.observe(deposit("a", "CONFIRMING", 2)).is_none());
+        assert_eq!(tracker.cursor(), 1_000);
+
+        let completed = tracker.observe(deposit("a", "COMPLETED", 3)).unwrap();
+        assert!(completed.confirmed);
+        assert!(tracker.observe(deposit("a", "COMPLETED", 4)).is_none());
+
+        // deposits into other vaults are ignored
+        let mut other = deposit("b", "COMPLETED", 1);
+        other.destination.as_mut().unwrap().id = Some("2".to_owned());
+        assert!(tracker.observe(other).is_none());
+    }
+
+    #[tokio::test]
+    async fn polls_every_page() {
+        let page = |id| serde_json::json!([deposit_json(id, "COMPLETED", 1)]);
+        let mock = MockApi::serve(vec![
+            MockResponse::new(200, page("c"))
+                .header("next-page", "{url}/v1/transactions?pageCursor=2"),
+            MockResponse::new(200, page("b"))
+                .header("next-page", "{url}/v1/transactions?pageCursor=3"),
+            MockResponse::new(200, page("a")),
+        ]);
+        let deposits = DepositMonitor::new(mock.client(), "1")
+            .since(0)
+            .stream()
+            .take(3)
+            .map(|deposit| deposit.unwrap().tx_id)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(deposits, ["c", "b", "a"]);
+        let requests = mock.requests();
+        assert!(requests[0].starts_with("GET /v1/transactions?"));
+        assert_eq!(requests[1], "GET /v1/transactions?pageCursor=2");
+        assert_eq!(requests[2], "GET /v1/transactions?pageCursor=3");
+    }
+}
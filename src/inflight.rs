@@ -0,0 +1,141 @@
+//! Registry of the transactions a signer is currently waiting on.
+use crate::{telemetry, types::TransactionStatus, Environment};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A submitted transaction which has not reached a final status yet
+pub struct InFlightTransaction {
+    /// The Fireblocks transaction id
+    pub id: String,
+    /// The last observed status
+    pub status: TransactionStatus,
+    /// The time since the transaction was submitted
+    pub age: Duration,
+    /// The number of tasks waiting on the transaction. This is more than one when identical
+    /// submissions get deduplicated into the same transaction.
+    pub waiters: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    status: TransactionStatus,
+    submitted_at: Instant,
+    waiters: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+/// Clones share the same registry
+pub(crate) struct InFlight {
+    environment: Environment,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl InFlight {
+    pub fn new(environment: Environment) -> Self {
+        Self {
+            environment,
+            entries: Default::default(),
+        }
+    }
+
+    /// Registers a task waiting on `id`. The task is unregistered when the guard is dropped.
+    pub fn track(&self, id: &str, status: TransactionStatus) -> InFlightGuard {
+        let mut entries = self.entries.lock().expect("in-flight lock poisoned");
+        entries
+            .entry(id.to_owned())
+            .or_insert_with(|| {
+                telemetry::in_flight(self.environment, 1.0);
+                Entry {
+                    status,
+                    submitted_at: Instant::now(),
+                    waiters: 0,
+                }
+            })
+            .waiters += 1;
+        InFlightGuard {
+            id: id.to_owned(),
+            registry: self.clone(),
+        }
+    }
+
+    /// Lists the in-flight transactions, oldest first
+    pub fn list(&self) -> Vec<InFlightTransaction> {
+        let entries = self.entries.lock().expect("in-flight lock poisoned");
+        let mut txs = entries
+            .iter()
+            .map(|(id, entry)| InFlightTransaction {
+                id: id.clone(),
+                status: entry.status,
+                age: entry.submitted_at.elapsed(),
+                waiters: entry.waiters,
+            })
+            .collect::<Vec<_>>();
+        txs.sort_by_key(|tx| std::cmp::Reverse(tx.age));
+        txs
+    }
+}
+
+/// Held by a task while it waits on a transaction
+#[derive(Debug)]
+pub(crate) struct InFlightGuard {
+    id: String,
+    registry: InFlight,
+}
+
+impl InFlightGuard {
+    /// Records the latest observed status of the transaction
+    pub fn update(&self, status: TransactionStatus) {
+        let mut entries = self
+            .registry
+            .entries
+            .lock()
+            .expect("in-flight lock poisoned");
+        if let Some(entry) = entries.get_mut(&self.id) {
+            entry.status = status;
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut entries = self
+            .registry
+            .entries
+            .lock()
+            .expect("in-flight lock poisoned");
+        if let Some(entry) = entries.get_mut(&self.id) {
+            entry.waiters -= 1;
+            if entry.waiters == 0 {
+                entries.remove(&self.id);
+                telemetry::in_flight(self.registry.environment, -1.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_waiters() {
+        let registry = InFlight::default();
+        let first = registry.track("a", TransactionStatus::SUBMITTED);
+        let second = registry.track("a", TransactionStatus::SUBMITTED);
+        first.update(TransactionStatus::PENDING_SIGNATURE);
+
+        let txs = registry.list();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].status, TransactionStatus::PENDING_SIGNATURE);
+        assert_eq!(txs[0].waiters, 2);
+
+        drop(first);
+        assert_eq!(registry.list()[0].waiters, 1);
+        drop(second);
+        assert!(registry.list().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Persistent mapping of intent ids to Fireblocks transaction ids.
+//!
+//! An intent id is the `externalTxId` a transaction is submitted with. Once Fireblocks
+//! accepts a transaction, its id is stored under the intent id, so that resubmitting the same
+//! intent (e.g. after a crash or a retried request) returns the original transaction instead
+//! of creating a second one, and so that in-flight intents can be recovered on restart
+//! through [`transaction_by_intent`](crate::FireblocksClient::transaction_by_intent).
+//!
+//! The store is independent of the in-memory dedupe window, which is keyed by the hash of the
+//! submitted body and forgets it after the window, and of the write-ahead journal, which
+//! records every body before it is sent.
+use crate::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+#[async_trait]
+/// Storage for the intent id to Fireblocks transaction id mapping
+pub trait IntentStore: std::fmt::Debug + Send + Sync {
+    /// Returns the id of the transaction submitted for `intent_id`, if any
+    async fn get(&self, intent_id: &str) -> Result<Option<String>>;
+
+    /// Stores the id of the transaction submitted for `intent_id`. Existing entries are
+    /// never overwritten.
+    async fn insert(&self, intent_id: &str, tx_id: &str) -> Result<()>;
+}
+
+#[derive(Debug, Default)]
+/// An in-memory [`IntentStore`], which does not survive restarts
+pub struct MemoryIntentStore {
+    entries: Mutex<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl IntentStore for MemoryIntentStore {
+    async fn get(&self, intent_id: &str) -> Result<Option<String>> {
+        let entries = self.entries.lock().expect("intent store lock poisoned");
+        Ok(entries.get(intent_id).cloned())
+    }
+
+    async fn insert(&self, intent_id: &str, tx_id: &str) -> Result<()> {
+        let mut entries = self.entries.lock().expect("intent store lock poisoned");
+        entries
+            .entry(intent_id.to_owned())
+            .or_insert_with(|| tx_id.to_owned());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntentRecord {
+    intent_id: String,
+    tx_id: String,
+}
+
+#[derive(Debug)]
+/// An [`IntentStore`] which appends each entry as a JSON line to a file, syncing it to disk
+/// before returning. The file is replayed into memory when opened.
+pub struct FileIntentStore {
+    file: Mutex<(File, HashMap<String, String>)>,
+}
+
+impl FileIntentStore {
+    /// Opens (or creates) the store file, loading its existing entries
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let mut entries = HashMap::new();
+        for line in BufReader::new(&file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: IntentRecord = serde_json::from_str(&line)?;
+            entries.entry(record.intent_id).or_insert(record.tx_id);
+        }
+        Ok(Self {
+            file: Mutex::new((file, entries)),
+        })
+    }
+}
+
+#[async_trait]
+impl IntentStore for FileIntentStore {
+    async fn get(&self, intent_id: &str) -> Result<Option<String>> {
+        let guard = self.file.lock().expect("intent store lock poisoned");
+        Ok(guard.1.get(intent_id).cloned())
+    }
+
+    async fn insert(&self, intent_id: &str, tx_id: &str) -> Result<()> {
+        let mut guard = self.file.lock().expect("intent store lock poisoned");
+        let (file, entries) = &mut *guard;
+        if entries.contains_key(intent_id) {
+            return Ok(());
+        }
+        let record = IntentRecord {
+            intent_id: intent_id.to_owned(),
+            tx_id: tx_id.to_owned(),
+        };
+        let mut line = serde_json::to_vec(&record).map_err(std::io::Error::from)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()?;
+        entries.insert(record.intent_id, record.tx_id);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+/// An [`IntentStore`] backed by Redis, which can be shared by several processes
+pub struct RedisIntentStore {
+    conn: redis::aio::MultiplexedConnection,
+    prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl std::fmt::Debug for RedisIntentStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisIntentStore")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+#[cfg(feature = "redis")]
+impl RedisIntentStore {
+    /// Connects to the Redis instance at `url`. Entries are stored under `prefix`, followed
+    /// by the intent id.
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let conn = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self {
+            conn,
+            prefix: prefix.to_owned(),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl IntentStore for RedisIntentStore {
+    async fn get(&self, intent_id: &str) -> Result<Option<String>> {
+        let mut conn = self.conn.clone();
+        Ok(redis::cmd("GET")
+            .arg(format!("{}{}", self.prefix, intent_id))
+            .query_async(&mut conn)
+            .await?)
+    }
+
+    async fn insert(&self, intent_id: &str, tx_id: &str) -> Result<()> {
+        let mut conn = self.conn.clone();
+        redis::cmd("SET")
+            .arg(format!("{}{}", self.prefix, intent_id))
+            .arg(tx_id)
+            .arg("NX")
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock_api::MockApi, types::TransactionArguments, FireblocksError};
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct ReadOnlyStore;
+
+    #[async_trait]
+    impl IntentStore for ReadOnlyStore {
+        async fn get(&self, _: &str) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn insert(&self, _: &str, _: &str) -> Result<()> {
+            Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied).into())
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_submissions_which_were_not_stored() {
+        let mock = MockApi::serve(vec![
+            serde_json::json!({ "id": "tx", "status": "SUBMITTED" }),
+        ]);
+        let mut client = mock.client();
+        client.intents = Some(Arc::new(ReadOnlyStore));
+        let tx: TransactionArguments = serde_json::from_value(serde_json::json!({
+            "assetId": "ETH",
+            "operation": "TRANSFER",
+            "source": { "type": "VAULT_ACCOUNT", "id": "0" },
+            "amount": "1",
+            "extraParameters": null,
+            "note": "",
+            "externalTxId": "payout-1",
+        }))
+        .unwrap();
+
+        // the transaction exists, so its id must reach the caller
+        match client.create_transaction(tx).await {
+            Err(FireblocksError::IntentNotStored {
+                intent_id, tx_id, ..
+            }) => {
+                assert_eq!(intent_id, "payout-1");
+                assert_eq!(tx_id, "tx");
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert_eq!(mock.bodies()[0]["externalTxId"], "payout-1");
+    }
+
+    #[tokio::test]
+    async fn file_store_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("fireblocks-intents-{}", std::process::id()));
+        {
+            let store = FileIntentStore::open(&path).unwrap();
+            store.insert("payout-1", "tx-1").await.unwrap();
+            store.insert("payout-1", "tx-2").await.unwrap();
+        }
+
+        let store = FileIntentStore::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            store.get("payout-1").await.unwrap().as_deref(),
+            Some("tx-1")
+        );
+        assert_eq!(store.get("payout-2").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Write-ahead journal of the transactions submitted to Fireblocks.
+//!
+//! Every `create_transaction` request is persisted *before* it is sent, so that operators
+//! can prove exactly what was requested when reconciling against Fireblocks' audit log.
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A single journal record
+pub struct JournalEntry {
+    /// Milliseconds since Epoch at which the entry was recorded
+    pub timestamp: u64,
+    /// The API path the body is posted to (e.g. `/v1/transactions`)
+    pub path: String,
+    /// Hex-encoded SHA-256 hash of the body, identical to the JWT's `bodyHash` claim
+    pub body_hash: String,
+    /// The body which is about to be sent
+    pub body: serde_json::Value,
+}
+
+impl JournalEntry {
+    pub(crate) fn new<S: Serialize>(path: &str, body: S) -> Result<Self, serde_json::Error> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Ok(Self {
+            timestamp,
+            path: path.to_owned(),
+            body_hash: crate::jwtclient::body_hash(&body)?,
+            body: serde_json::to_value(body)?,
+        })
+    }
+}
+
+/// A sink for [`JournalEntry`] records. If recording fails, the submission is aborted.
+pub trait Journal: std::fmt::Debug + Send + Sync {
+    fn record(&self, entry: &JournalEntry) -> std::io::Result<()>;
+}
+
+#[derive(Debug)]
+/// Appends each entry as a JSON line to a file, syncing it to disk before returning.
+pub struct FileJournal {
+    file: Mutex<File>,
+}
+
+impl FileJournal {
+    /// Opens (or creates) the journal file in append mode
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Journal for FileJournal {
+    fn record(&self, entry: &JournalEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().expect("journal lock poisoned");
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("fireblocks-journal-{}", std::process::id()));
+        let journal = FileJournal::open(&path).unwrap();
+        for note in ["first", "second"] {
+            let entry =
+                JournalEntry::new("/v1/transactions", serde_json::json!({ "note": note })).unwrap();
+            journal.record(&entry).unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let entries = contents
+            .lines()
+            .map(|line| serde_json::from_str::<JournalEntry>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].body["note"], "second");
+        assert_eq!(
+            entries[0].body_hash,
+            crate::jwtclient::body_hash(serde_json::json!({ "note": "first" })).unwrap()
+        );
+    }
+}
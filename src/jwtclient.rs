@@ -4,30 +4,38 @@ use digest::Digest;
 use rustc_hex::ToHex;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 use thiserror::Error;
-use rand::Rng;
 
 const EXPIRY: u64 = 55;
 
 #[derive(Debug, Clone)]
 pub struct JwtSigner {
     // TODO: Make this work with Zeroize/Secrecy
-    pub key: EncodingKey,
+    pub key: Arc<EncodingKey>,
     pub api_key: String,
+    nonces: NonceSource,
 }
 
 impl JwtSigner {
     pub fn new(key: EncodingKey, api_key: &str) -> Self {
         Self {
-            key,
+            key: Arc::new(key),
             api_key: api_key.to_string(),
+            nonces: NonceSource::default(),
         }
     }
 
-    pub fn sign<S: Serialize>(&self, path: &str, body: S) -> Result<String, JwtError> {
+    /// Signs a JWT for a request to `path` with the provided serialized body
+    pub fn sign(&self, path: &str, body: &[u8]) -> Result<String, JwtError> {
         let header = Header::new(Algorithm::RS256);
-        let claims = Claims::new(path, &self.api_key, body)?;
+        let claims = Claims::new(path, &self.api_key, body, self.nonces.next()?)?;
         Ok(jsonwebtoken::encode(&header, &claims, &self.key)?)
     }
 }
@@ -50,6 +58,25 @@ struct Claims<'a> {
     body_hash: String,
 }
 
+#[derive(Debug, Clone, Default)]
+/// Hands out strictly increasing JWT nonces. Clones share the same counter, so that clients
+/// cloned across many tasks never reuse a nonce, even within the same millisecond.
+struct NonceSource(Arc<AtomicU64>);
+
+impl NonceSource {
+    /// Returns the current time in microseconds, or the last nonce + 1 if that is larger
+    fn next(&self) -> Result<u64, JwtError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64;
+        let prev = self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .expect("closure always returns Some");
+        Ok(now.max(prev + 1))
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum JwtError {
     #[error("Could not serialize JWT body: {0}")]
@@ -61,26 +88,59 @@ pub enum JwtError {
 }
 
 impl<'a> Claims<'a> {
-    fn new<S: Serialize>(uri: &'a str, sub: &'a str, body: S) -> Result<Self, JwtError> {
-        // use millisecond precision to ensure that it's not reused
-        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
-        let mut rng = rand::thread_rng();
-        let nonce = rng.gen::<u64>();
-        let now = now / 1000;
-
-        let body_hash = {
-            let mut digest = Sha256::new();
-            digest.update(serde_json::to_vec(&body)?);
-            digest.finalize().to_vec()
-        };
+    fn new(uri: &'a str, sub: &'a str, body: &[u8], nonce: u64) -> Result<Self, JwtError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
         Ok(Self {
             uri,
             sub,
-            body_hash: body_hash.to_hex::<String>(),
+            body_hash: hash_bytes(body),
             nonce,
             iat: now,
             exp: now + EXPIRY,
         })
     }
 }
+
+/// Hex-encoded SHA-256 hash of the JSON serialized body
+pub fn body_hash<S: Serialize>(body: S) -> Result<String, serde_json::Error> {
+    Ok(hash_bytes(&serde_json::to_vec(&body)?))
+}
+
+/// Hex-encoded SHA-256 hash of the raw body
+pub fn hash_bytes(body: &[u8]) -> String {
+    let mut digest = Sha256::new();
+    digest.update(body);
+    digest.finalize().to_vec().to_hex::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn nonces_are_unique_across_clones() {
+        let source = NonceSource::default();
+        let handles = (0..8)
+            .map(|_| {
+                let source = source.clone();
+                std::thread::spawn(move || {
+                    let nonces = (0..1000)
+                        .map(|_| source.next().unwrap())
+                        .collect::<Vec<_>>();
+                    // strictly increasing within each task
+                    assert!(nonces.windows(2).all(|w| w[0] < w[1]));
+                    nonces
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for nonce in handle.join().unwrap() {
+                assert!(seen.insert(nonce));
+            }
+        }
+    }
+}
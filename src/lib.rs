@@ -3,10 +3,14 @@
 //! Provides [ethers](https://docs.rs/ethers)-compatible Signer and Middleware
 //! implementations for the Fireblocks API.
 //!
+//! The underlying API client, [`FireblocksClient`], does not depend on ethers. Disable the
+//! default `ethers` feature to use it on its own.
+//!
 //! ```rust,no_run
+//! # #[cfg(feature = "ethers")]
 //! # async fn broadcasts_tx() -> Result<(), Box<dyn std::error::Error>> {
 //! use ethers_providers::{Middleware, Provider};
-//! use ethers_core::types::Address;
+//! use ethers_core::types::{transaction::eip2718::TypedTransaction, Address};
 //! use ethers_fireblocks::{FireblocksSigner, FireblocksMiddleware, Config};
 //! use std::convert::TryFrom;
 //!
@@ -31,20 +35,89 @@
 //! # }
 //! ```
 mod jwtclient;
-mod types;
-use types::{TransactionArguments, TransactionDetails, TransactionStatus};
+pub mod types;
+#[cfg(feature = "ethers")]
+use types::{
+    AssetResponse, CreateVaultRequest, PeerType, StakingBalances, TransactionArguments,
+    TransactionDetails, VaultAccountResponse,
+};
+use types::{TransactionStatus, WalletAssetStatus};
 
 mod api;
-use api::FireblocksClient;
+pub use api::{Environment, FireblocksClient};
+
+pub mod contracts;
 
+#[cfg(feature = "ethers")]
 mod signer;
+#[cfg(feature = "ethers")]
+pub use signer::{FeeBreakdown, SharedSigner};
 
+#[cfg(feature = "ethers")]
 mod middleware;
-pub use middleware::FireblocksMiddleware;
+#[cfg(feature = "ethers")]
+pub use middleware::{
+    FireblocksMiddleware, FireblocksPendingTransaction, TransactionOptions,
+    MAX_DESTINATION_TAG_LENGTH,
+};
+
+mod telemetry;
+
+mod dedupe;
+
+pub mod intents;
+
+pub mod deposits;
+
+#[cfg(feature = "ethers")]
+mod sandbox;
+#[cfg(feature = "ethers")]
+pub use sandbox::SANDBOX_CHAIN_ID;
+
+#[cfg(feature = "ethers")]
+mod queue;
+#[cfg(feature = "ethers")]
+pub use queue::Priority;
+
+#[cfg(feature = "ethers")]
+mod poll;
+#[cfg(feature = "ethers")]
+pub use poll::{PollInterval, TerminalStatuses};
 
-use ethers_core::types::Address;
+#[cfg(feature = "ethers")]
+mod inflight;
+#[cfg(feature = "ethers")]
+pub use inflight::InFlightTransaction;
+
+pub mod webhook;
+
+#[cfg(feature = "ethers")]
+mod cache;
+#[cfg(feature = "ethers")]
+pub use cache::{VaultCache, VaultChange};
+
+#[cfg(feature = "journal")]
+pub mod journal;
+
+#[cfg(test)]
+mod mock_api;
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+#[cfg(feature = "ethers")]
+use ethers_core::types::{transaction::eip2718::TypedTransaction, Address};
+#[cfg(feature = "ethers")]
 use jsonwebtoken::EncodingKey;
-use std::{collections::HashMap, time::Instant};
+#[cfg(feature = "ethers")]
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 
 pub(crate) type Result<T> = std::result::Result<T, FireblocksError>;
@@ -68,6 +141,23 @@ pub enum FireblocksError {
     /// Thrown when submitting a POST/GET request fails
     ReqwestError(#[from] reqwest::Error),
 
+    #[cfg(feature = "redis")]
+    #[error(transparent)]
+    /// Thrown when the Redis intent store fails
+    RedisError(#[from] redis::RedisError),
+
+    #[error("Fireblocks API error (HTTP {status}): {message}")]
+    /// Thrown when the API responds with a non-2xx status
+    ApiError {
+        status: u16,
+        code: Option<i64>,
+        message: String,
+    },
+
+    #[error("Fireblocks did not complete the operation at {0}")]
+    /// Thrown when a management endpoint responds with `{ "success": false }`
+    OperationFailed(String),
+
     #[error("Deserialization Error: {err}. Response: {text}")]
     /// Serde JSON Error
     SerdeJson {
@@ -86,10 +176,159 @@ pub enum FireblocksError {
     /// Thrown when parsing string as Ethereum data fails
     ParseError(String),
 
+    #[error("Timed out while waiting for transaction {0} to be broadcast")]
+    /// Thrown when an approved transaction is not broadcast within the broadcast timeout
+    BroadcastTimeout(String),
+
     #[error("Timed out while waiting for user to approve transaction")]
-    Timeout,
+    /// Thrown when a transaction is not approved within the approval timeout. If it was to be
+    /// [cancelled](FireblocksSigner::cancel_on_timeout) but cancelling it failed, carries the
+    /// error of the cancellation, since the transaction may then still get approved.
+    Timeout {
+        #[source]
+        cancel_error: Option<Box<FireblocksError>>,
+    },
+
+    #[error("The signer is paused and does not submit transactions")]
+    /// Thrown when trying to submit a transaction while the signer is paused
+    Paused,
+
+    #[error("Whitelisted wallet {wallet_id} cannot be used for {asset_id} yet: {status:?}")]
+    /// Thrown when the destination's whitelist entry is not approved for the asset
+    WalletNotApproved {
+        wallet_id: String,
+        asset_id: String,
+        status: Option<WalletAssetStatus>,
+    },
+
+    #[error("Transaction {0} was not approved before its deadline and got cancelled")]
+    /// Thrown when a transaction is not approved within its approval deadline
+    ApprovalExpired(String),
+
+    #[error("Chain id {0} is not supported by Fireblocks")]
+    /// Thrown when there is no Fireblocks asset for the requested chain id
+    UnsupportedChainId(u64),
+
+    #[error("The provider is on chain {provider}, but the signer is on chain {signer}")]
+    /// Thrown when the middleware's provider and signer are connected to different chains
+    ChainIdMismatch { signer: u64, provider: u64 },
+
+    #[error("Transaction is sent from {got:?}, but the vault's address is {expected:?}")]
+    /// Thrown when a transaction's `from` is not the signer's vault address
+    #[cfg(feature = "ethers")]
+    SenderMismatch { expected: Address, got: Address },
+
+    #[error(
+        "Signature does not recover to the signer's address {expected:?}, but to {recovered:?}"
+    )]
+    /// Thrown when a signature returned by Fireblocks does not recover to the signer's
+    /// address with either parity
+    #[cfg(feature = "ethers")]
+    SignatureMismatch {
+        expected: Address,
+        recovered: Option<Address>,
+    },
+
+    #[error("Transaction {tx_id} was rejected by AML screening: {sub_status}")]
+    /// Thrown when a transaction is stopped by AML screening. Carries the screening
+    /// provider's verdict, if Fireblocks reported one.
+    AmlRejected {
+        tx_id: String,
+        status: TransactionStatus,
+        sub_status: String,
+        screening: Option<Box<types::AmlScreeningResult>>,
+    },
+
+    #[error("Timed out while transaction {0} was pending AML screening")]
+    /// Thrown when the timeout elapses while the transaction is still being screened
+    AmlScreeningPending(String),
+
+    #[error(
+        "Typed data must be signed with sign_typed_data_message, so that approvers see the \
+         structured data, unless RAW signing of its digest is enabled"
+    )]
+    /// Thrown by [`Signer::sign_typed_data`](ethers_signers::Signer::sign_typed_data), which
+    /// only has access to the digest of the payload, unless
+    /// [`raw_typed_data`](FireblocksSigner::raw_typed_data) is enabled
+    TypedDataRequired,
+
+    #[error("Deposit address {0} not found in the vault account")]
+    /// Thrown when the selected deposit address is not one of the vault's addresses
+    DepositAddressNotFound(String),
+
+    #[error("Invalid destination tag {tag:?}: {reason}")]
+    /// Thrown when a transaction's destination tag cannot be sent, e.g. because it is too
+    /// long or the transaction has no destination address
+    InvalidDestinationTag { tag: String, reason: &'static str },
+
+    #[error("Transaction {tx_id} was submitted, but could not be stored under intent {intent_id}")]
+    /// Thrown when Fireblocks accepted a transaction but the
+    /// [intent store](FireblocksSigner::intent_store) failed to record it. The transaction
+    /// exists, so it must not be resubmitted blindly.
+    IntentNotStored {
+        intent_id: String,
+        tx_id: String,
+        #[source]
+        source: Box<FireblocksError>,
+    },
+
+    #[error("Note is {length} characters long, at most {max} are allowed")]
+    /// Thrown when a transaction's note exceeds the signer's
+    /// [`max_note_length`](FireblocksSigner::max_note_length)
+    NoteTooLong { length: usize, max: usize },
+}
+
+impl FireblocksError {
+    /// Whether the error is likely to go away on retry: a failed connection, a timeout, a
+    /// rate limit (429) or a temporarily unavailable server (500, 502, 503 or 504). Other
+    /// request errors, e.g. an invalid url or an unimplemented endpoint, fail the same way on
+    /// every attempt.
+    pub fn is_transient(&self) -> bool {
+        let transient_status = |status: u16| matches!(status, 429 | 500 | 502 | 503 | 504);
+        match self {
+            FireblocksError::ReqwestError(err) => {
+                err.is_timeout()
+                    || err.is_connect()
+                    || err
+                        .status()
+                        .is_some_and(|status| transient_status(status.as_u16()))
+            }
+            FireblocksError::ApiError { status, .. } => transient_status(*status),
+            _ => false,
+        }
+    }
+
+    /// Whether the error is the outcome of AML screening, which usually calls for
+    /// escalation to compliance rather than a retry
+    pub fn is_aml(&self) -> bool {
+        matches!(
+            self,
+            FireblocksError::AmlRejected { .. } | FireblocksError::AmlScreeningPending(_)
+        )
+    }
+
+    #[cfg(feature = "ethers")]
+    fn from_details(details: TransactionDetails) -> Self {
+        if details.is_aml_rejected() {
+            FireblocksError::AmlRejected {
+                tx_id: details.id,
+                status: details.status,
+                sub_status: details.sub_status,
+                screening: details.aml_screening_result.map(Box::new),
+            }
+        } else {
+            FireblocksError::TxError(details.status, details.sub_status)
+        }
+    }
 }
 
+/// The default longest note which is submitted to Fireblocks. Fireblocks' API reference does
+/// not document a limit for `note`, so this is a conservative default which keeps notes
+/// readable in the console. It can be changed with
+/// [`max_note_length`](FireblocksSigner::max_note_length).
+pub const MAX_NOTE_LENGTH: usize = 4096;
+
+#[cfg(feature = "ethers")]
 #[derive(Debug, Clone)]
 /// FireblocksSigner is a [`Signer`](ethers_signers::Signer) which utilizes Fireblocks'
 /// MPC signing over its [API](https://docs.fireblocks.io/api) instead of a local private key.
@@ -99,16 +338,46 @@ pub enum FireblocksError {
 ///
 /// Consider using [`FireblocksMiddleware`](crate::FireblocksMiddleware) to have an integrated
 /// ethers [`Middleware`](eters_middleware::Middleware) experience.
+///
+/// Cloning the signer is cheap, as its client, keys and registered accounts are behind `Arc`s,
+/// so a signer can be shared across tasks by cloning it, or by reference through
+/// [`FireblocksSigner::shared`].
 pub struct FireblocksSigner {
     fireblocks: FireblocksClient,
-    account_ids: HashMap<Address, String>,
+    account_ids: Arc<HashMap<Address, (PeerType, String)>>,
     chain_id: u64,
     asset_id: String,
     address: Address,
+    // which of the vault's deposit addresses the signer represents, kept to re-select it when
+    // switching chains
+    deposit_address: DepositAddress,
     account_id: String,
-    timeout: u128,
+    approval_timeout: Duration,
+    broadcast_timeout: Duration,
+    poll_interval: poll::PollInterval,
+    poll_retries: u32,
+    webhooks: Option<webhook::WebhookDispatcher>,
+    checksum_addresses: bool,
+    paused: Arc<AtomicBool>,
+    calldata_explorer: Option<String>,
+    abis: Arc<HashMap<Address, ethers_core::abi::Abi>>,
+    verify_whitelist: bool,
+    queue: Option<queue::SubmissionQueue>,
+    default_note: Option<middleware::Generator>,
+    default_external_tx_id: Option<middleware::Generator>,
+    full_raw_note: bool,
+    max_note_length: usize,
+    typed_messages: bool,
+    raw_typed_data: bool,
+    in_flight: inflight::InFlight,
+    fee_level: Option<types::FeeLevel>,
+    force_contract_call: bool,
+    cancel_on_timeout: bool,
+    status_hooks: poll::StatusHooks,
+    terminal_statuses: TerminalStatuses,
 }
 
+#[cfg(feature = "ethers")]
 /// Configuration options for instantiating a [`FireblocksSigner`](FireblocksSigner)
 pub struct Config {
     /// The RSA key file.
@@ -119,8 +388,78 @@ pub struct Config {
     pub chain_id: u64,
     /// Your vault's account id.
     pub account_id: String,
+    /// Credentials to fall back to when the primary ones are rejected, which allows
+    /// rotating API keys without downtime.
+    pub secondary: Option<Credentials>,
+    /// The Fireblocks environment to connect to. Defaults to production.
+    pub environment: Environment,
+    /// Whether the signer starts out paused, see [`FireblocksSigner::pause`].
+    pub paused: bool,
+    /// Which of the vault's deposit addresses the signer represents. Defaults to the first one.
+    pub deposit_address: Option<DepositAddress>,
+    /// Whether the signer registers the workspace's whitelisted wallets when it is
+    /// instantiated, see [`FireblocksSigner::sync_known_destinations`].
+    pub resolve_destinations: bool,
+}
+
+#[cfg(feature = "ethers")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Selects one of a vault account's deposit addresses
+pub enum DepositAddress {
+    /// The address at this position in the vault's list of deposit addresses
+    Index(usize),
+    /// This address, which must be one of the vault's deposit addresses
+    Address(Address),
 }
 
+#[cfg(feature = "ethers")]
+impl DepositAddress {
+    fn select(&self, addresses: &[types::DepositAddressResponse]) -> Result<Address> {
+        let mut addresses = addresses
+            .iter()
+            .map(|res| parse_vault_address(&res.address));
+        match *self {
+            DepositAddress::Index(index) => addresses
+                .nth(index)
+                .ok_or_else(|| FireblocksError::DepositAddressNotFound(format!("#{}", index)))?,
+            DepositAddress::Address(address) => {
+                for candidate in addresses {
+                    if candidate? == address {
+                        return Ok(address);
+                    }
+                }
+                Err(FireblocksError::DepositAddressNotFound(format!(
+                    "{:?}",
+                    address
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ethers")]
+/// An RSA key and its associated API key
+pub struct Credentials {
+    /// The RSA key file.
+    pub key: EncodingKey,
+    /// The API key which was provided to you by fireblocks support
+    pub api_key: String,
+}
+
+#[cfg(feature = "ethers")]
+impl Credentials {
+    /// Instantiates the credentials given a path to the RSA file and the API key.
+    pub fn new<T: AsRef<str>>(key: T, api_key: &str) -> Result<Self> {
+        let rsa_pem = std::fs::read(key.as_ref())?;
+        let key = EncodingKey::from_rsa_pem(&rsa_pem)?;
+        Ok(Self {
+            key,
+            api_key: api_key.to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "ethers")]
 impl Config {
     /// Instantiates the config file given a path to the RSA file as well as the rest of the config
     /// args.
@@ -138,83 +477,769 @@ impl Config {
             chain_id,
             api_key: api_key.to_string(),
             account_id: account_id.to_string(),
+            secondary: None,
+            environment: Environment::Production,
+            paused: false,
+            deposit_address: None,
+            resolve_destinations: false,
         })
     }
+
+    /// Sets the Fireblocks environment to connect to.
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Sets the secondary credentials which are used whenever the primary ones get
+    /// rejected with a 401, e.g. while an API key is being rotated.
+    pub fn with_secondary(mut self, secondary: Credentials) -> Self {
+        self.secondary = Some(secondary);
+        self
+    }
+
+    /// Sets which of the vault's deposit addresses the signer represents
+    pub fn with_deposit_address(mut self, deposit_address: DepositAddress) -> Self {
+        self.deposit_address = Some(deposit_address);
+        self
+    }
+
+    /// Registers the workspace's whitelisted wallets when the signer is instantiated, so that
+    /// transactions to their addresses are attributed to them without any `add_account` calls
+    pub fn with_resolved_destinations(mut self) -> Self {
+        self.resolve_destinations = true;
+        self
+    }
 }
 
+#[cfg(feature = "ethers")]
 impl AsRef<FireblocksClient> for FireblocksSigner {
     fn as_ref(&self) -> &FireblocksClient {
         &self.fireblocks
     }
 }
 
+#[cfg(feature = "ethers")]
 impl FireblocksSigner {
     /// Instantiates a FireblocksSigner with the provided config
     pub async fn new(cfg: Config) -> Self {
-        let fireblocks = FireblocksClient::new(cfg.key, &cfg.api_key);
-        let asset_id = match cfg.chain_id {
-            1 => "ETH",
-            3 => "ETH_TEST",
-            5 => "ETH_TEST3",
-            42 => "ETH_TEST2",
-            _ => panic!("Unsupported chain_id"),
+        let fireblocks = Self::client(&cfg);
+        let deposit_address = cfg.deposit_address.unwrap_or(DepositAddress::Index(0));
+        let mut signer =
+            Self::from_client_at(fireblocks, &cfg.account_id, cfg.chain_id, deposit_address)
+                .await
+                .expect("could not instantiate signer");
+        if cfg.resolve_destinations {
+            signer
+                .sync_known_destinations()
+                .await
+                .expect("could not fetch whitelisted wallets");
+        }
+        if cfg.paused {
+            signer.pause();
+        }
+        signer
+    }
+
+    /// Instantiates a FireblocksSigner for the provided vault account from an existing client,
+    /// sharing its credentials and connection pool.
+    pub async fn from_client(
+        fireblocks: FireblocksClient,
+        account_id: &str,
+        chain_id: u64,
+    ) -> Result<Self> {
+        Self::from_client_at(fireblocks, account_id, chain_id, DepositAddress::Index(0)).await
+    }
+
+    /// Instantiates a FireblocksSigner for the selected deposit address of the provided vault
+    /// account, from an existing client
+    pub async fn from_client_at(
+        fireblocks: FireblocksClient,
+        account_id: &str,
+        chain_id: u64,
+        deposit_address: DepositAddress,
+    ) -> Result<Self> {
+        let asset_id = asset_id(chain_id)?;
+        let res = fireblocks.vault_addresses(account_id, asset_id).await?;
+        let address = deposit_address.select(&res)?;
+
+        let mut signer = Self::with_client(
+            fireblocks,
+            account_id.to_owned(),
+            chain_id,
+            asset_id.to_owned(),
+            address,
+        );
+        signer.deposit_address = deposit_address;
+        Ok(signer)
+    }
+
+    /// Returns a handle which signs and submits from the vault account `account_id` instead,
+    /// reusing this signer's client and settings. The handle shares the signer's pause switch
+    /// and in-flight registry, but gets its own submission queue, as submissions from
+    /// different vault accounts do not conflict.
+    pub async fn for_account(&self, account_id: &str) -> Result<Self> {
+        let res = self
+            .fireblocks
+            .vault_addresses(account_id, &self.asset_id)
+            .await?;
+        let address = DepositAddress::Index(0).select(&res)?;
+
+        let mut signer = self.clone();
+        signer.account_id = account_id.to_owned();
+        signer.address = address;
+        if signer.queue.is_some() {
+            signer.queue = Some(queue::SubmissionQueue::default());
+        }
+        Ok(signer)
+    }
+
+    /// Creates a new vault account named `name`, creates and activates its `asset` wallet
+    /// along with the one of the chain's native asset (which pays for gas), and returns a
+    /// signer for `cfg.chain_id`. If `cfg.account_id` is set, the wallets are provisioned in
+    /// that vault account instead of a new one. The rest of the config applies as with
+    /// [`new`](Self::new).
+    pub async fn create_vault_and_signer(name: &str, asset: &str, cfg: Config) -> Result<Self> {
+        let fireblocks = Self::client(&cfg);
+        let native = asset_id(cfg.chain_id)?;
+        let account_id = if cfg.account_id.is_empty() {
+            fireblocks
+                .new_vault(CreateVaultRequest {
+                    name: name.to_owned(),
+                    hidden_on_ui: false,
+                    customer_ref_id: None,
+                    auto_fuel: false,
+                })
+                .await?
+                .id
+        } else {
+            cfg.account_id.clone()
         };
 
-        let res = fireblocks
-            .vault_addresses(&cfg.account_id, asset_id)
-            .await
-            .expect("could not get vault addrs");
+        let mut assets = vec![native];
+        if asset != native {
+            assets.push(asset);
+        }
+        for asset in assets {
+            // the wallet does not exist yet for new vaults
+            let exists = fireblocks
+                .vault_addresses(&account_id, asset)
+                .await
+                .is_ok_and(|addresses| !addresses.is_empty());
+            if !exists {
+                fireblocks.new_vault_wallet(&account_id, asset).await?;
+            }
+            fireblocks.activate_wallet(&account_id, asset).await?;
+        }
+
+        let deposit_address = cfg.deposit_address.unwrap_or(DepositAddress::Index(0));
+        let mut signer =
+            Self::from_client_at(fireblocks, &account_id, cfg.chain_id, deposit_address).await?;
+        if cfg.resolve_destinations {
+            signer.sync_known_destinations().await?;
+        }
+        if cfg.paused {
+            signer.pause();
+        }
+        Ok(signer)
+    }
+
+    /// Cancels a transaction whose approval deadline has passed, recording the expiry in the
+    /// journal.
+    async fn expire(&self, txid: &str, deadline: Duration) -> Result<()> {
+        let reason = serde_json::json!({
+            "reason": "approval deadline expired",
+            "deadlineMs": deadline.as_millis() as u64,
+        });
+        self.abandon(txid, reason).await
+    }
 
+    /// Cancels a transaction which the signer stopped waiting for, recording why in the
+    /// journal (if any)
+    async fn abandon(&self, txid: &str, reason: serde_json::Value) -> Result<()> {
+        #[cfg(feature = "journal")]
+        self.fireblocks
+            .record(&format!("transactions/{}/cancel", txid), reason)?;
+        #[cfg(not(feature = "journal"))]
+        let _ = reason;
+        self.fireblocks.cancel_transaction(txid).await
+    }
+
+    /// Cancels a Fireblocks transaction which has not been signed yet, e.g. one which is stuck
+    /// waiting for approval
+    pub async fn cancel_transaction(&self, txid: &str) -> Result<()> {
+        self.fireblocks.cancel_transaction(txid).await
+    }
+
+    /// Drops a Fireblocks transaction which is stuck in the mempool by replacing it with a
+    /// 0-value self-send at `fee_level` (or at a fee picked by Fireblocks)
+    pub async fn drop_transaction(
+        &self,
+        txid: &str,
+        fee_level: Option<types::FeeLevel>,
+    ) -> Result<types::DropTransactionResponse> {
+        let fee = types::DropTransactionRequest {
+            fee_level,
+            gas_price: None,
+        };
+        self.fireblocks.drop_transaction(txid, &fee).await
+    }
+
+    fn client(cfg: &Config) -> FireblocksClient {
+        let mut fireblocks =
+            FireblocksClient::new_with_url(cfg.key.clone(), &cfg.api_key, cfg.environment.url())
+                .with_environment(cfg.environment);
+        if let Some(ref secondary) = cfg.secondary {
+            fireblocks = fireblocks.with_secondary(secondary.key.clone(), &secondary.api_key);
+        }
+        fireblocks
+    }
+
+    fn with_client(
+        fireblocks: FireblocksClient,
+        account_id: String,
+        chain_id: u64,
+        asset_id: String,
+        address: Address,
+    ) -> Self {
+        let in_flight = inflight::InFlight::new(fireblocks.environment());
         Self {
             fireblocks,
-            account_ids: HashMap::new(),
-            chain_id: cfg.chain_id,
-            asset_id: asset_id.to_owned(),
-            address: res[0].address[2..]
-                .parse()
-                .expect("could not parse as address"),
-            account_id: cfg.account_id,
-            timeout: 60_000,
+            account_ids: Default::default(),
+            chain_id,
+            asset_id,
+            address,
+            deposit_address: DepositAddress::Index(0),
+            account_id,
+            approval_timeout: Duration::from_secs(60),
+            broadcast_timeout: Duration::from_secs(60),
+            poll_interval: Default::default(),
+            poll_retries: 3,
+            webhooks: None,
+            checksum_addresses: true,
+            paused: Arc::new(AtomicBool::new(false)),
+            calldata_explorer: None,
+            abis: Default::default(),
+            verify_whitelist: false,
+            queue: None,
+            default_note: None,
+            default_external_tx_id: None,
+            full_raw_note: false,
+            max_note_length: MAX_NOTE_LENGTH,
+            typed_messages: false,
+            raw_typed_data: false,
+            in_flight,
+            fee_level: None,
+            force_contract_call: false,
+            cancel_on_timeout: false,
+            status_hooks: Default::default(),
+            terminal_statuses: Default::default(),
         }
     }
 
-    /// Sets the timeout duration in milliseconds. If the user does not approve a
-    /// transaction within this time, the transaction request throws an error.
-    pub fn timeout(&mut self, timeout_ms: u128) {
-        self.timeout = timeout_ms;
+    /// Fetches the signer's vault account, including the balances of all of its assets
+    /// (see [`AssetResponse::balances`](types::AssetResponse::balances)).
+    pub async fn vault(&self) -> Result<VaultAccountResponse> {
+        self.fireblocks.vault(&self.account_id).await
+    }
+
+    /// Fetches the signer's vault wallet, i.e. the balances of its asset
+    pub async fn wallet(&self) -> Result<AssetResponse> {
+        self.fireblocks
+            .vault_wallet(&self.account_id, &self.asset_id)
+            .await
+    }
+
+    /// Fetches the staking balances of the signer's vault wallet
+    pub async fn staking_balances(&self) -> Result<StakingBalances> {
+        self.wallet().await?.staking_balances()
+    }
+
+    /// Sets the journal to which every transaction is persisted before being submitted.
+    #[cfg(feature = "journal")]
+    pub fn journal(&mut self, journal: std::sync::Arc<dyn journal::Journal>) {
+        self.fireblocks.journal = Some(journal);
+    }
+
+    /// Returns a monitor of the deposits into the signer's vault account
+    pub fn deposits(&self) -> deposits::DepositMonitor {
+        deposits::DepositMonitor::new(self.fireblocks.clone(), &self.account_id)
+    }
+
+    /// Sets the store which maps intent ids (the `externalTxId` of a transaction) to the
+    /// Fireblocks transactions submitted for them. Submitting an intent which is already in
+    /// the store returns the original transaction instead of creating a new one.
+    pub fn intent_store(&mut self, store: std::sync::Arc<dyn intents::IntentStore>) {
+        self.fireblocks.intents = Some(store);
+    }
+
+    /// Lists the transactions the signer is currently waiting on, oldest first. Useful to
+    /// report the backlog and to detect approvals which have been pending for too long.
+    pub fn in_flight(&self) -> Vec<InFlightTransaction> {
+        self.in_flight.list()
+    }
+
+    /// The number of submissions waiting for their turn in the
+    /// [submission queue](Self::submission_queue), or 0 if it is disabled
+    pub fn queue_depth(&self) -> usize {
+        self.queue.as_ref().map_or(0, |queue| queue.depth())
+    }
+
+    /// Sets whether the note of RAW signing requests contains the full serialized transaction
+    /// or message. By default it only contains a summary (the hash, and the destination and
+    /// function selector of transactions), which keeps payloads out of the console history.
+    pub fn full_raw_note(&mut self, enabled: bool) {
+        self.full_raw_note = enabled;
+    }
+
+    /// Sets the longest note, in characters, which is submitted to Fireblocks. Transactions
+    /// and signing requests with longer notes fail with [`FireblocksError::NoteTooLong`]
+    /// before submission. Defaults to [`MAX_NOTE_LENGTH`].
+    pub fn max_note_length(&mut self, length: usize) {
+        self.max_note_length = length;
+    }
+
+    /// Sets whether [`sign_message`](ethers_signers::Signer::sign_message) signs with the
+    /// `TYPED_MESSAGE` operation (see [`sign_personal_message`](Self::sign_personal_message))
+    /// instead of RAW signing the message's hash.
+    pub fn typed_messages(&mut self, enabled: bool) {
+        self.typed_messages = enabled;
+    }
+
+    /// Sets whether [`sign_typed_data`](ethers_signers::Signer::sign_typed_data) RAW signs the
+    /// EIP712 digest of the payload. The `Eip712` trait only exposes the digest, so approvers
+    /// cannot see what they are signing: by default it fails with
+    /// [`TypedDataRequired`](FireblocksError::TypedDataRequired), and typed data is signed
+    /// with the `TYPED_MESSAGE` operation through
+    /// [`sign_typed_data_message`](Self::sign_typed_data_message) instead.
+    pub fn raw_typed_data(&mut self, enabled: bool) {
+        self.raw_typed_data = enabled;
+    }
+
+    /// Sets a calldata decoder url which gets linked in the note of every contract call, so
+    /// that approvers can inspect what they are signing. Any `{calldata}` placeholder in the
+    /// url is replaced by the 0x-prefixed calldata, otherwise the calldata is appended.
+    pub fn calldata_explorer(&mut self, url: &str) {
+        self.calldata_explorer = Some(url.to_owned());
+    }
+
+    /// Registers the ABI of the contract at `address`. The note of every call to the contract
+    /// then includes the decoded function and arguments, e.g. `transfer(to: 0x…, amount: 5)`.
+    pub fn register_abi(&mut self, address: Address, abi: ethers_core::abi::Abi) {
+        Arc::make_mut(&mut self.abis).insert(address, abi);
+    }
+
+    /// Enables deduplication of submissions: submitting the exact same transaction arguments
+    /// twice within `window` returns the original Fireblocks transaction instead of creating a
+    /// duplicate one. Protects against accidental double submissions upstream.
+    pub fn dedupe_window(&mut self, window: std::time::Duration) {
+        self.fireblocks.dedupe = Some(dedupe::DedupeWindow::new(window));
+    }
+
+    /// Sets whether the whitelist status of external wallet destinations is checked before
+    /// submitting a transaction. When enabled, submissions to wallets which are not yet
+    /// approved for the signer's asset fail with [`FireblocksError::WalletNotApproved`]
+    /// instead of getting BLOCKED by Fireblocks later on.
+    pub fn verify_whitelist(&mut self, enabled: bool) {
+        self.verify_whitelist = enabled;
+    }
+
+    /// Enables the submission queue: submissions from this vault account are let through one
+    /// at a time, each waiting until Fireblocks accepted (or rejected) the previous one. Waiting
+    /// submissions are served by their [`Priority`](TransactionOptions::priority), so that
+    /// urgent operations jump ahead of routine ones. Clones of the signer share the queue.
+    pub fn submission_queue(&mut self, enabled: bool) {
+        self.queue = enabled.then(queue::SubmissionQueue::default);
+    }
+
+    /// Sets whether transactions without calldata are submitted with the `CONTRACT_CALL`
+    /// operation, like the ones with calldata. By default they are submitted as `TRANSFER`s,
+    /// so that the workspace's transfer policies apply to them.
+    pub fn force_contract_call(&mut self, enabled: bool) {
+        self.force_contract_call = enabled;
+    }
+
+    /// Sets whether transactions which are still waiting for approval when the
+    /// [approval timeout](Self::approval_timeout) elapses get cancelled, so that they cannot
+    /// be approved and broadcast after the caller gave up on them. Defaults to `false`, and
+    /// can be overridden per transaction through [`TransactionOptions::cancel_on_timeout`].
+    pub fn cancel_on_timeout(&mut self, enabled: bool) {
+        self.cancel_on_timeout = enabled;
+    }
+
+    /// Sets the statuses at which the signer stops waiting for a transaction, unless they are
+    /// overridden through [`TransactionOptions::terminal_statuses`]. Defaults to succeeding
+    /// at `COMPLETED` and failing at `BLOCKED`, `CANCELLED`, `REJECTED` and `FAILED`. Only
+    /// applies to transaction submissions: signing requests always wait for the defaults.
+    pub fn terminal_statuses(&mut self, statuses: TerminalStatuses) {
+        self.terminal_statuses = statuses;
+    }
+
+    /// Registers a callback which fires whenever a transaction the signer waits for reaches
+    /// `status`, e.g. to page the approvers when it reaches `PENDING_SIGNATURE`. Callbacks
+    /// must not block, since they run on the task waiting for the transaction.
+    pub fn on_status<F>(&mut self, status: TransactionStatus, hook: F)
+    where
+        F: Fn(&TransactionDetails) + Send + Sync + 'static,
+    {
+        self.status_hooks.add(status, Arc::new(hook));
+    }
+
+    /// Registers a callback which fires whenever a transaction the signer waits for collects
+    /// an approval, e.g. to report that 2 of 3 approvals were collected
+    pub fn on_approval_progress<F>(&mut self, hook: F)
+    where
+        F: Fn(&TransactionDetails, types::ApprovalProgress) + Send + Sync + 'static,
+    {
+        self.status_hooks.add_progress(Arc::new(hook));
+    }
+
+    /// Registers a callback which fires whenever a transaction waits for its approvers to
+    /// sign it. See [`on_status`](Self::on_status).
+    pub fn on_pending_signature<F>(&mut self, hook: F)
+    where
+        F: Fn(&TransactionDetails) + Send + Sync + 'static,
+    {
+        self.on_status(TransactionStatus::PENDING_SIGNATURE, hook);
+    }
+
+    /// Registers a callback which fires whenever a transaction starts being broadcast. See
+    /// [`on_status`](Self::on_status).
+    pub fn on_broadcasting<F>(&mut self, hook: F)
+    where
+        F: Fn(&TransactionDetails) + Send + Sync + 'static,
+    {
+        self.on_status(TransactionStatus::BROADCASTING, hook);
+    }
+
+    /// Sets the fee level at which Fireblocks prices submitted transactions which do not
+    /// specify one through their [`TransactionOptions`], instead of their gas price or fees.
+    /// Friendlier than explicit prices on networks whose fees are spiky.
+    pub fn default_fee_level(&mut self, level: Option<types::FeeLevel>) {
+        self.fee_level = level;
+    }
+
+    /// Sets a function which generates the note of every submitted transaction which does not
+    /// specify one through its [`TransactionOptions`].
+    pub fn default_note<F>(&mut self, f: F)
+    where
+        F: Fn(&TypedTransaction) -> String + Send + Sync + 'static,
+    {
+        self.default_note = Some(middleware::Generator::new(f));
+    }
+
+    /// Sets a function which generates the `externalTxId` of every submitted transaction
+    /// which does not specify one through its [`TransactionOptions`], e.g. a ULID.
+    pub fn default_external_tx_id<F>(&mut self, f: F)
+    where
+        F: Fn(&TypedTransaction) -> String + Send + Sync + 'static,
+    {
+        self.default_external_tx_id = Some(middleware::Generator::new(f));
+    }
+
+    /// Pauses the signer: until [`resume`](Self::resume) is called, any method which would
+    /// submit a transaction or a signing request returns [`FireblocksError::Paused`], while
+    /// read-only methods keep working. The switch is shared by all clones of the signer.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resumes a paused signer.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns whether the signer is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Sets both the [approval](Self::approval_timeout) and the
+    /// [broadcast](Self::broadcast_timeout) timeouts.
+    pub fn timeout(&mut self, timeout: Duration) {
+        self.approval_timeout = timeout;
+        self.broadcast_timeout = timeout;
+    }
+
+    /// Sets how long a transaction may wait for approval (and for AML screening). If it is
+    /// not approved within this time, the transaction request throws an error. Defaults to
+    /// 60 seconds.
+    pub fn approval_timeout(&mut self, timeout: Duration) {
+        self.approval_timeout = timeout;
+    }
+
+    /// Sets how long an approved transaction may take to be signed and broadcast (or
+    /// confirmed, when waiting for completion). Defaults to 60 seconds.
+    pub fn broadcast_timeout(&mut self, timeout: Duration) {
+        self.broadcast_timeout = timeout;
+    }
+
+    /// Sets how often the status of a submitted transaction is polled while waiting for it.
+    /// Defaults to every 1 to 2 seconds, with jitter.
+    pub fn poll_interval(&mut self, poll_interval: PollInterval) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Sets how many times a status poll which fails with a
+    /// [transient](FireblocksError::is_transient) error is retried before the error is
+    /// returned. Defaults to 3.
+    pub fn poll_retries(&mut self, retries: u32) {
+        self.poll_retries = retries;
+    }
+
+    /// Waits for the webhook events handed to `dispatcher` instead of polling the status of
+    /// submitted transactions at the poll interval. An event makes the signer fetch the
+    /// transaction's status, which is never taken from the event itself. Polling only resumes
+    /// while no event arrives within the dispatcher's fallback interval.
+    pub fn webhook_dispatcher(&mut self, dispatcher: webhook::WebhookDispatcher) {
+        self.webhooks = Some(dispatcher);
     }
 
     /// Registers an Account ID to Address mapping.
     pub fn add_account(&mut self, account_id: String, address: Address) {
-        self.account_ids.insert(address, account_id);
+        Arc::make_mut(&mut self.account_ids)
+            .insert(address, (PeerType::EXTERNAL_WALLET, account_id));
+    }
+
+    /// Fetches the workspace's internal, external and contract wallets and registers the
+    /// address of each wallet holding the signer's asset, so that transactions to them are
+    /// attributed to the wallet instead of a one-time address. Returns the number of
+    /// registered wallets.
+    pub async fn sync_known_destinations(&mut self) -> Result<usize> {
+        let internal = self.fireblocks.internal_wallets().await?;
+        let external = self.fireblocks.external_wallets().await?;
+        let contracts = self.fireblocks.contracts().await?;
+        let wallets = internal
+            .into_iter()
+            .map(|wallet| (PeerType::INTERNAL_WALLET, wallet))
+            .chain(
+                external
+                    .into_iter()
+                    .map(|wallet| (PeerType::EXTERNAL_WALLET, wallet)),
+            )
+            .chain(
+                contracts
+                    .into_iter()
+                    .map(|wallet| (PeerType::CONTRACT, wallet)),
+            );
+
+        let mut registered = 0;
+        for (peer_type, wallet) in wallets {
+            let address = wallet
+                .assets
+                .iter()
+                .filter(|asset| asset.id == self.asset_id)
+                .find_map(|asset| asset.address.as_deref());
+            if let Some(address) = address {
+                let address = parse_vault_address(address)?;
+                Arc::make_mut(&mut self.account_ids).insert(address, (peer_type, wallet.id));
+                registered += 1;
+            }
+        }
+        Ok(registered)
+    }
+
+    /// Registers an Account ID to Address mapping from a hex string. Mixed-case addresses
+    /// must carry a valid EIP-55 checksum.
+    pub fn add_account_str(&mut self, account_id: String, address: &str) -> Result<()> {
+        let address = middleware::parse_address(address)?;
+        self.add_account(account_id, address);
+        Ok(())
+    }
+
+    /// Sets whether destination addresses are sent to Fireblocks EIP-55 checksummed
+    /// (the default) or as lowercase hex.
+    pub fn checksum_addresses(&mut self, enabled: bool) {
+        self.checksum_addresses = enabled;
     }
 
-    async fn handle_action<F, R>(&self, args: TransactionArguments, func: F) -> Result<R>
+    async fn handle_action<F, R>(
+        &self,
+        args: TransactionArguments,
+        options: &TransactionOptions,
+        func: F,
+    ) -> Result<R>
     where
         F: FnOnce(TransactionDetails) -> Result<R>,
     {
-        let res = self.fireblocks.create_transaction(args).await?;
+        let res = {
+            // the next submission may go as soon as Fireblocks accepted this one, instead
+            // of waiting for it to be approved
+            let _permit = match self.queue {
+                Some(ref queue) => Some(queue.acquire(options.priority).await),
+                None => None,
+            };
+            self.create(args).await?
+        };
+        self.wait_for(&res.id, res.status, options, self.timeouts(), func)
+            .await
+    }
+
+    async fn create(&self, args: TransactionArguments) -> Result<types::CreateTransactionResponse> {
+        if self.is_paused() {
+            return Err(FireblocksError::Paused);
+        }
+        // see `Signer::with_chain_id`
+        if args.asset_id.is_empty() {
+            return Err(FireblocksError::UnsupportedChainId(self.chain_id));
+        }
+        self.check_note(&args.note)?;
+        self.fireblocks.create_transaction(args).await
+    }
+
+    fn check_note(&self, note: &str) -> Result<()> {
+        let length = note.chars().count();
+        if length > self.max_note_length {
+            return Err(FireblocksError::NoteTooLong {
+                length,
+                max: self.max_note_length,
+            });
+        }
+        Ok(())
+    }
+
+    /// The statuses at which waiting with `options` stops
+    fn terminal_statuses_for<'a>(
+        &'a self,
+        options: &'a TransactionOptions,
+    ) -> &'a TerminalStatuses {
+        options
+            .terminal_statuses
+            .as_ref()
+            .unwrap_or(&self.terminal_statuses)
+    }
+
+    /// The signer's `(approval, broadcast)` timeouts
+    fn timeouts(&self) -> (Duration, Duration) {
+        (self.approval_timeout, self.broadcast_timeout)
+    }
+
+    /// Waits for the transaction `txid`, last seen with `status`, to reach the status
+    /// `options` wait for, and maps its details with `func`. The transaction may spend up to
+    /// the first of the `timeouts` waiting for approval, and then up to the second one being
+    /// broadcast.
+    async fn wait_for<F, R>(
+        &self,
+        txid: &str,
+        mut status: TransactionStatus,
+        options: &TransactionOptions,
+        (approval_timeout, broadcast_timeout): (Duration, Duration),
+        func: F,
+    ) -> Result<R>
+    where
+        F: FnOnce(TransactionDetails) -> Result<R>,
+    {
+        let txid = txid.to_owned();
+        let in_flight = self.in_flight.track(&txid, status);
         let start = Instant::now();
+        let mut phase_start = start;
+        let mut approving = true;
+        let mut last_status = None;
+        let mut last_progress = None;
+        let mut poller = poll::Poller::new(self, &txid);
         loop {
-            if Instant::now().duration_since(start).as_millis() >= self.timeout {
-                return Err(FireblocksError::Timeout);
+            let timeout = if approving {
+                approval_timeout
+            } else {
+                broadcast_timeout
+            };
+            if phase_start.elapsed() >= timeout {
+                if !approving {
+                    return Err(FireblocksError::BroadcastTimeout(txid));
+                }
+                let mut cancel_error = None;
+                if options.cancel_on_timeout.unwrap_or(self.cancel_on_timeout) {
+                    let reason = serde_json::json!({
+                        "reason": "timed out",
+                        "timeoutMs": timeout.as_millis() as u64,
+                    });
+                    // the timeout is what the caller needs to know about, a failed
+                    // cancellation is attached to it
+                    cancel_error = self.abandon(&txid, reason).await.err().map(Box::new);
+                }
+                if status == TransactionStatus::PENDING_AML_SCREENING {
+                    return Err(FireblocksError::AmlScreeningPending(txid));
+                }
+                return Err(FireblocksError::Timeout { cancel_error });
             }
 
-            let details = self.fireblocks.transaction(&res.id).await?;
+            let details = poller.current().await?;
+            if last_status != Some(details.status) {
+                self.status_hooks.fire(&details);
+                last_status = Some(details.status);
+            }
+            let progress = details.approval_progress();
+            if progress != last_progress {
+                if let Some(progress) = progress {
+                    self.status_hooks.fire_progress(&details, progress);
+                }
+                last_progress = progress;
+            }
+            status = details.status;
+            in_flight.update(details.status);
+            if approving
+                && !status.is_pending_approval()
+                && status != TransactionStatus::PENDING_AML_SCREENING
+            {
+                approving = false;
+                phase_start = Instant::now();
+            }
             use TransactionStatus::*;
             // Loops in pending signature
-            match details.status {
-                BROADCASTING | COMPLETED => return func(details),
-                BLOCKED | CANCELLED | FAILED => {
-                    return Err(FireblocksError::TxError(details.status, details.sub_status))
+            if let Some(deadline) = options.approval_deadline {
+                if details.status.is_pending_approval() && start.elapsed() >= deadline {
+                    self.expire(&txid, deadline).await?;
+                    return Err(FireblocksError::ApprovalExpired(txid));
                 }
-                _ => {}
             }
+            let terminal = self.terminal_statuses_for(options);
+            // the transaction hash is known once the transaction is broadcast, confirmations
+            // are better awaited on-chain
+            let broadcast = matches!(details.status, BROADCASTING | CONFIRMING)
+                && !options.wait_until_completed
+                && !details.tx_hash.is_empty();
+            if broadcast || terminal.success.contains(&details.status) {
+                telemetry::approval_latency(self.fireblocks.environment(), start.elapsed());
+                return func(details);
+            }
+            if terminal.failure.contains(&details.status) {
+                return Err(FireblocksError::from_details(details));
+            }
+            let timeout = if approving {
+                approval_timeout
+            } else {
+                broadcast_timeout
+            };
+            poller
+                .wait(timeout.saturating_sub(phase_start.elapsed()))
+                .await;
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(feature = "ethers")]
+/// Returns the Fireblocks asset id of the native asset of the provided chain
+fn asset_id(chain_id: u64) -> Result<&'static str> {
+    Ok(match chain_id {
+        1 => "ETH",
+        3 => "ETH_TEST",
+        5 => "ETH_TEST3",
+        42 => "ETH_TEST2",
+        11155111 => "ETH_TEST5",
+        _ => return Err(FireblocksError::UnsupportedChainId(chain_id)),
+    })
+}
+
+#[cfg(feature = "ethers")]
+/// Parses a 0x-prefixed address as returned by the vault endpoints
+fn parse_vault_address(address: &str) -> Result<Address> {
+    address
+        .trim_start_matches("0x")
+        .parse()
+        .map_err(|err: rustc_hex::FromHexError| FireblocksError::ParseError(err.to_string()))
+}
+
+#[cfg(all(test, feature = "ethers"))]
 async fn test_signer() -> FireblocksSigner {
     let config = Config::new(
         std::env::var("FIREBLOCKS_API_SECRET_PATH").unwrap(),
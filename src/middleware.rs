@@ -1,35 +1,481 @@
 use ethers_core::types::{
-    transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, NameOrAddress, Signature,
-    TxHash,
+    transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes, NameOrAddress,
+    Signature, Transaction, TransactionRequest, TxHash, U256,
 };
-use ethers_providers::{MiddlewareError, Middleware, PendingTransaction};
+use ethers_core::{
+    abi::{Abi, Token},
+    utils::{format_units, to_checksum},
+};
+use ethers_providers::{JsonRpcClient, Middleware, MiddlewareError, PendingTransaction};
 use ethers_signers::Signer;
 
 use crate::{
     types::{
-        DestinationTransferPeerPath, ExtraParameters, OneTimeAddress, PeerType,
-        TransactionArguments, TransactionOperation, TransferPeerPath,
+        CreateTransactionResponse, DestinationTransferPeerPath, DropTransactionResponse,
+        ExtraParameters, FeeLevel, OneTimeAddress, PeerType, TransactionArguments,
+        TransactionDetails, TransactionOperation, TransactionStatus, TransferPeerPath,
+        WalletAssetStatus,
     },
-    FireblocksError, FireblocksSigner,
+    FeeBreakdown, FireblocksClient, FireblocksError, FireblocksSigner, Priority, TerminalStatuses,
 };
 use async_trait::async_trait;
 use rustc_hex::ToHex;
+use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
+use tokio::sync::Mutex;
 
 #[derive(Debug)]
 /// The `FireblocksMiddleware` is an ethers-compatible middleware which sends transactions
 /// and signs messages using Fireblocks' API. Sending transactions utilizes the `CONTRACT_CALL`
-/// mode and signing messages utilizes the `RAW` mode.
+/// mode (or `TRANSFER` for plain value transfers) and signing messages utilizes the `RAW`
+/// mode.
+///
+/// Transactions and messages are signed with the vault of the signer the middleware was
+/// created with, unless their sender is the address of another vault added with
+/// [`add_vault`](Self::add_vault).
 pub struct FireblocksMiddleware<M> {
     fireblocks: FireblocksSigner,
+    // the signers of the other vaults, by address
+    vaults: HashMap<Address, FireblocksSigner>,
     inner: M,
+    // the next nonce of each vault address, fetched on first use
+    nonce: Option<Arc<Mutex<HashMap<Address, U256>>>>,
 }
 
 impl<M: Middleware> FireblocksMiddleware<M> {
     /// Creates a new FireblocksMiddleware.
     pub fn new(inner: M, fireblocks: FireblocksSigner) -> Self {
-        Self { inner, fireblocks }
+        Self {
+            inner,
+            fireblocks,
+            vaults: HashMap::new(),
+            nonce: None,
+        }
+    }
+
+    /// Creates a new FireblocksMiddleware after checking that `inner` is connected to the
+    /// signer's chain, since signatures for another chain get rejected by the provider with
+    /// confusing errors
+    pub async fn new_checked(
+        inner: M,
+        fireblocks: FireblocksSigner,
+    ) -> Result<Self, FireblocksMiddlewareError<M>> {
+        let chain_id = inner
+            .get_chainid()
+            .await
+            .map_err(FireblocksMiddlewareError::MiddlewareError)?;
+        if chain_id != U256::from(fireblocks.chain_id()) {
+            return Err(FireblocksError::ChainIdMismatch {
+                signer: fireblocks.chain_id(),
+                provider: chain_id.low_u64(),
+            }
+            .into());
+        }
+        Ok(Self::new(inner, fireblocks))
+    }
+
+    /// Adds the signer of another vault account. Transactions sent from its address are
+    /// submitted from that vault, and messages signed for its address are signed by it.
+    /// [`FireblocksSigner::for_account`] creates such a signer from an existing one.
+    pub fn add_vault(&mut self, signer: FireblocksSigner) {
+        self.vaults.insert(signer.address(), signer);
+    }
+
+    /// Returns the signer of the vault with address `from`, or the default signer if there
+    /// is no sender or no vault with that address
+    fn signer_for(&self, from: Option<&Address>) -> &FireblocksSigner {
+        from.and_then(|from| self.vaults.get(from))
+            .unwrap_or(&self.fireblocks)
+    }
+
+    /// Enables nonce management for transactions signed with the RAW operation (transactions
+    /// filled with `fill_transaction` and then signed with `sign_transaction`): the middleware
+    /// tracks each vault address's pending nonce, so that concurrently signed transactions do
+    /// not reuse nonces. The nonce is part of the signed payload sent to Fireblocks.
+    /// Transactions submitted with `send_transaction` do not draw from the tracker, since the
+    /// CONTRACT_CALL / TRANSFER API has no nonce and Fireblocks assigns it itself. When filling
+    /// or signing fails, the address's nonce is fetched again on next use, so that a failed
+    /// submission does not leave a gap.
+    pub fn manage_nonces(&mut self, enabled: bool) {
+        self.nonce = enabled.then(Default::default);
+    }
+
+    /// Forgets the tracked nonces, so that they are fetched again on next use, e.g. after a
+    /// transaction was dropped
+    pub async fn reset_nonce(&self) {
+        if let Some(ref nonce) = self.nonce {
+            nonce.lock().await.clear();
+        }
     }
+
+    async fn next_nonce(
+        &self,
+        address: Address,
+    ) -> Result<Option<U256>, FireblocksMiddlewareError<M>> {
+        let mut nonces = match self.nonce {
+            Some(ref nonce) => nonce.lock().await,
+            None => return Ok(None),
+        };
+        let next = match nonces.get(&address) {
+            Some(next) => *next,
+            None => self
+                .inner
+                .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+                .await
+                .map_err(FireblocksMiddlewareError::MiddlewareError)?,
+        };
+        nonces.insert(address, next + 1);
+        Ok(Some(next))
+    }
+
+    // Forgets the tracked nonce of `address` after `used`, a nonce drawn for it, went unused,
+    // so that it is fetched again from the pending nonce on next use
+    async fn release_nonce(&self, address: Address, used: U256) {
+        if let Some(ref nonce) = self.nonce {
+            let mut nonces = nonce.lock().await;
+            if nonces.get(&address).is_some_and(|next| used < *next) {
+                nonces.remove(&address);
+            }
+        }
+    }
+
+    /// The signer used to submit transactions and sign messages
+    pub fn signer(&self) -> &FireblocksSigner {
+        &self.fireblocks
+    }
+
+    /// Mutable access to the signer, e.g. to change its settings
+    pub fn signer_mut(&mut self) -> &mut FireblocksSigner {
+        &mut self.fireblocks
+    }
+
+    /// The API client of the signer
+    pub fn fireblocks(&self) -> &FireblocksClient {
+        self.fireblocks.as_ref()
+    }
+
+    /// Consumes the middleware, returning the inner middleware and the signer
+    pub fn into_inner(self) -> (M, FireblocksSigner) {
+        (self.inner, self.fireblocks)
+    }
+
+    /// Re-submits the stuck transaction `tx_hash` with the gas price `gas_price`, replacing it
+    /// through Fireblocks' `replaceTxByHash`. The replacement is otherwise identical to the
+    /// original transaction, which is fetched from the inner provider.
+    pub async fn speed_up(
+        &self,
+        tx_hash: TxHash,
+        gas_price: U256,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        let original = self.stuck_transaction(tx_hash).await?;
+        let mut tx = TransactionRequest::new()
+            .from(original.from)
+            .value(original.value)
+            .data(original.input)
+            .gas(original.gas)
+            .gas_price(gas_price)
+            .nonce(original.nonce);
+        tx.to = original.to.map(Into::into);
+        let options = TransactionOptions {
+            note: Some(format!("Speed up {:?}", tx_hash)),
+            replace_tx_by_hash: Some(tx_hash),
+            ..Default::default()
+        };
+        self.send(tx.into(), None, options).await
+    }
+
+    /// Cancels the stuck transaction `tx_hash` by replacing it with an empty transfer from its
+    /// vault to itself, priced at the `HIGH` fee level
+    pub async fn cancel_onchain(
+        &self,
+        tx_hash: TxHash,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        let original = self.stuck_transaction(tx_hash).await?;
+        let vault = original.from;
+        let tx = TransactionRequest::new()
+            .from(vault)
+            .to(vault)
+            .value(0)
+            .nonce(original.nonce);
+        let options = TransactionOptions {
+            note: Some(format!("Cancel {:?}", tx_hash)),
+            fee_level: Some(FeeLevel::HIGH),
+            replace_tx_by_hash: Some(tx_hash),
+            ..Default::default()
+        };
+        self.send(tx.into(), None, options).await
+    }
+
+    /// Fetches a completed transaction and returns a breakdown of the fees it paid, see
+    /// [`FireblocksSigner::fee_paid`]. The gas used and the effective gas price are taken from
+    /// the transaction's receipt.
+    pub async fn fee_paid(&self, txid: &str) -> Result<FeeBreakdown, FireblocksMiddlewareError<M>> {
+        let details = self.fireblocks.fireblocks.transaction(txid).await?;
+        let mut fees = self.fireblocks.fees_of(&details).await?;
+        let (tx_hash, _) = with_tx_hash(details)?;
+        let receipt = self
+            .inner
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(FireblocksMiddlewareError::MiddlewareError)?;
+        if let Some(receipt) = receipt {
+            fees.gas_used = receipt.gas_used;
+            fees.gas_price = receipt.effective_gas_price.or(fees.gas_price);
+        }
+        Ok(fees)
+    }
+
+    /// Cancels a Fireblocks transaction which has not been signed yet, see
+    /// [`FireblocksSigner::cancel_transaction`]
+    pub async fn cancel_transaction(&self, txid: &str) -> Result<(), FireblocksError> {
+        self.fireblocks.cancel_transaction(txid).await
+    }
+
+    /// Drops a Fireblocks transaction which is stuck in the mempool, see
+    /// [`FireblocksSigner::drop_transaction`]
+    pub async fn drop_transaction(
+        &self,
+        txid: &str,
+        fee_level: Option<FeeLevel>,
+    ) -> Result<DropTransactionResponse, FireblocksError> {
+        self.fireblocks.drop_transaction(txid, fee_level).await
+    }
+
+    async fn stuck_transaction(
+        &self,
+        tx_hash: TxHash,
+    ) -> Result<Transaction, FireblocksMiddlewareError<M>> {
+        self.inner
+            .get_transaction(tx_hash)
+            .await
+            .map_err(FireblocksMiddlewareError::MiddlewareError)?
+            .ok_or_else(|| {
+                FireblocksError::ParseError(format!("transaction {:?} not found", tx_hash)).into()
+            })
+    }
+
+    /// Submits a transaction with `note` as the note shown to approvers and returns a pending
+    /// transaction object
+    pub async fn send_transaction_with_note<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        note: &str,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        let options = TransactionOptions {
+            note: Some(note.to_owned()),
+            ..Default::default()
+        };
+        self.send_transaction_with_options(tx, options).await
+    }
+
+    /// Submits a transaction with the provided Fireblocks-specific options and returns a
+    /// pending transaction object. The gas limit and fees which are not set are filled in
+    /// with the inner provider's estimates.
+    pub async fn send_transaction_with_options<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        options: TransactionOptions,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        self.send(tx.into(), None, options).await
+    }
+
+    /// Transfers `amount` of the ERC-20 token `asset_id` from the default vault to `to` and
+    /// returns a pending transaction object. See [`FireblocksSigner::transfer_erc20`].
+    pub async fn transfer_erc20(
+        &self,
+        asset_id: &str,
+        to: Address,
+        amount: U256,
+        decimals: u32,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        let tx_hash = self
+            .fireblocks
+            .transfer_erc20(asset_id, to, amount, decimals)
+            .await?;
+        Ok(PendingTransaction::new(tx_hash, self.provider()))
+    }
+
+    /// Re-attaches to the Fireblocks transaction `tx_id`, e.g. after a restart, and returns a
+    /// pending transaction object once it is broadcast. See
+    /// [`FireblocksSigner::resume_transaction`].
+    pub async fn resume_transaction(
+        &self,
+        tx_id: &str,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        let tx_hash = self.fireblocks.resume_transaction(tx_id).await?;
+        Ok(PendingTransaction::new(tx_hash, self.provider()))
+    }
+
+    /// Submits a transaction with the provided options and returns a pending transaction
+    /// object which also carries the id of the Fireblocks transaction, e.g. to cancel it or
+    /// to correlate it with webhook notifications
+    pub async fn send_transaction_with_id<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        options: TransactionOptions,
+    ) -> Result<FireblocksPendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        self.send_with_id(tx.into(), None, options).await
+    }
+
+    async fn send(
+        &self,
+        tx: TypedTransaction,
+        block: Option<BlockId>,
+        options: TransactionOptions,
+    ) -> Result<PendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        Ok(self.send_with_id(tx, block, options).await?.pending)
+    }
+
+    /// Fills `tx` and builds the Fireblocks request submitting it. Contract deployments are
+    /// `CONTRACT_CALL`s without a destination, with the init code as calldata.
+    async fn send_arguments(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+        options: &TransactionOptions,
+    ) -> Result<TransactionArguments, FireblocksMiddlewareError<M>> {
+        if tx.from().is_none() {
+            tx.set_from(self.fireblocks.address());
+        }
+        // Fireblocks assigns the nonce of the transactions it submits, so none is drawn from
+        // the tracker
+        self.inner
+            .fill_transaction(tx, block)
+            .await
+            .map_err(FireblocksMiddlewareError::MiddlewareError)?;
+        Ok(self
+            .signer_for(tx.from())
+            .transaction_arguments(tx, options)?)
+    }
+
+    async fn send_with_id(
+        &self,
+        mut tx: TypedTransaction,
+        block: Option<BlockId>,
+        options: TransactionOptions,
+    ) -> Result<FireblocksPendingTransaction<'_, M::Provider>, FireblocksMiddlewareError<M>> {
+        let args = self.send_arguments(&mut tx, block, &options).await?;
+        let (tx_hash, details) = self
+            .signer_for(tx.from())
+            .submit_arguments(args, &options)
+            .await?;
+        Ok(FireblocksPendingTransaction {
+            id: details.id,
+            pending: PendingTransaction::new(tx_hash, self.provider()),
+        })
+    }
+}
+
+/// A transaction submitted through Fireblocks: the pending on-chain transaction along with the
+/// id of the Fireblocks transaction. Dereferences to the [`PendingTransaction`], and resolves
+/// to its receipt when awaited.
+pub struct FireblocksPendingTransaction<'a, P> {
+    /// The id of the Fireblocks transaction
+    pub id: String,
+    /// The pending on-chain transaction
+    pub pending: PendingTransaction<'a, P>,
+}
+
+impl<'a, P> std::ops::Deref for FireblocksPendingTransaction<'a, P> {
+    type Target = PendingTransaction<'a, P>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pending
+    }
+}
+
+impl<'a, P: JsonRpcClient> std::future::Future for FireblocksPendingTransaction<'a, P> {
+    type Output = <PendingTransaction<'a, P> as std::future::Future>::Output;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        std::pin::Pin::new(&mut self.pending).poll(cx)
+    }
+}
+
+impl<'a, P> std::fmt::Debug for FireblocksPendingTransaction<'a, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FireblocksPendingTransaction")
+            .field("id", &self.id)
+            .field("tx_hash", &*self.pending)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+/// Generates a per-transaction value (e.g. a note) from the transaction
+pub(crate) struct Generator(Arc<dyn Fn(&TypedTransaction) -> String + Send + Sync>);
+
+impl Generator {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&TypedTransaction) -> String + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    fn generate(&self, tx: &TypedTransaction) -> String {
+        (self.0)(tx)
+    }
+}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Generator")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Fireblocks-specific options for a single transaction submission
+pub struct TransactionOptions {
+    /// The note shown to approvers in the Fireblocks console
+    pub note: Option<String>,
+    /// An internal customer or order id, which Fireblocks includes in its reporting and AML
+    /// screening
+    pub customer_ref_id: Option<String>,
+    /// A unique id for the transaction, which Fireblocks uses to reject duplicate
+    /// submissions
+    pub external_tx_id: Option<String>,
+    /// The destination tag or memo of the recipient, e.g. the memo identifying the account of
+    /// an exchange deposit address. Invalid tags (empty, longer than
+    /// [`MAX_DESTINATION_TAG_LENGTH`] or without a destination address to attach them to) fail
+    /// with [`FireblocksError::InvalidDestinationTag`] instead of being dropped.
+    pub destination_tag: Option<String>,
+    /// Lets Fireblocks price the transaction at this level, instead of using the gas price
+    /// or fees of the transaction. Overrides the signer's
+    /// [default fee level](FireblocksSigner::default_fee_level).
+    pub fee_level: Option<FeeLevel>,
+    /// The hash of a stuck transaction which this one replaces, by re-using its nonce
+    pub replace_tx_by_hash: Option<TxHash>,
+    /// Whether to wait for Fireblocks to report the transaction as COMPLETED, i.e. confirmed,
+    /// before returning. By default the transaction hash is returned as soon as the
+    /// transaction is broadcast, and confirmations are left to the `PendingTransaction`.
+    pub wait_until_completed: bool,
+    /// Fail the transaction instead of broadcasting it if its fee is too low for the network
+    pub fail_on_low_fee: Option<bool>,
+    /// Send the whole balance of the source, for assets whose addresses must be emptied
+    pub force_sweep: Option<bool>,
+    /// Deduct the fee from the amount instead of adding it on top
+    pub treat_as_gross_amount: Option<bool>,
+    /// How long approvers have to approve the transaction after it is submitted. Once the
+    /// deadline passes, the transaction is cancelled and the expiry is recorded in the
+    /// journal (if any). Useful for quotes which are only valid briefly.
+    pub approval_deadline: Option<std::time::Duration>,
+    /// The ABI of the called contract, used to decode the call into the note. Overrides the
+    /// ABI [registered](FireblocksSigner::register_abi) for the contract.
+    pub abi: Option<Abi>,
+    /// The statuses at which to stop waiting for the transaction. Overrides the signer's
+    /// [terminal statuses](FireblocksSigner::terminal_statuses).
+    pub terminal_statuses: Option<crate::TerminalStatuses>,
+    /// Whether to cancel the transaction if it is still waiting for approval when the signer's
+    /// timeout elapses. Overrides the signer's
+    /// [setting](FireblocksSigner::cancel_on_timeout).
+    pub cancel_on_timeout: Option<bool>,
+    /// The priority of the transaction in the signer's
+    /// [submission queue](FireblocksSigner::submission_queue), if enabled
+    pub priority: Priority,
 }
 
 // Boilerplate
@@ -56,7 +502,6 @@ pub enum FireblocksMiddlewareError<M: Middleware> {
     MiddlewareError(M::Error),
 }
 
-
 #[async_trait]
 impl<M: Middleware> Middleware for FireblocksMiddleware<M> {
     type Provider = M::Provider;
@@ -67,18 +512,70 @@ impl<M: Middleware> Middleware for FireblocksMiddleware<M> {
         &self.inner
     }
 
-    /// Submits a transaction with the Fireblocks CONTRACT_CALL mode and returns
-    /// a pending transaction object.
+    /// Transactions without a sender are sent from the default vault's address
+    fn default_sender(&self) -> Option<Address> {
+        Some(self.fireblocks.address())
+    }
+
+    /// Fills the transaction with the inner middleware, sending it from the vault's address
+    /// so that gas is estimated against its balance and state
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        if tx.from().is_none() {
+            tx.set_from(self.fireblocks.address());
+        }
+        let from = self.signer_for(tx.from()).address();
+        check_sender(tx.from(), from)?;
+        let mut drawn = None;
+        if tx.nonce().is_none() {
+            if let Some(nonce) = self.next_nonce(from).await? {
+                tx.set_nonce(nonce);
+                drawn = Some(nonce);
+            }
+        }
+        let res = self
+            .inner
+            .fill_transaction(tx, block)
+            .await
+            .map_err(FireblocksMiddlewareError::MiddlewareError);
+        if let (Err(_), Some(nonce)) = (&res, drawn) {
+            self.release_nonce(from, nonce).await;
+        }
+        res
+    }
+
+    /// Signs a transaction using Fireblocks' Signer. Uses the RAW operation mode under
+    /// the hood. If signing fails, a nonce drawn for the transaction by
+    /// [`fill_transaction`](Self::fill_transaction) is given back.
+    async fn sign_transaction(
+        &self,
+        tx: &TypedTransaction,
+        from: Address,
+    ) -> Result<Signature, Self::Error> {
+        let signer = self.signer_for(Some(&from));
+        let res = match check_sender(Some(&from), signer.address()) {
+            Ok(()) => signer.sign_transaction(tx).await,
+            Err(err) => Err(err),
+        };
+        if let (Err(_), Some(nonce)) = (&res, tx.nonce()) {
+            self.release_nonce(from, *nonce).await;
+        }
+        Ok(res?)
+    }
+
+    /// Submits a transaction with the Fireblocks CONTRACT_CALL (or TRANSFER) mode and returns
+    /// a pending transaction object. Contract deployments are submitted as CONTRACT_CALLs
+    /// without a destination.
     async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
         &self,
         tx: T,
-        _: Option<BlockId>,
+        block: Option<BlockId>,
     ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
-        let tx_hash = self
-            .fireblocks
-            .submit_transaction(tx, "".to_owned())
-            .await?;
-        Ok(PendingTransaction::new(tx_hash, self.provider()))
+        self.send(tx.into(), block, TransactionOptions::default())
+            .await
     }
 
     /// Signs a message using Fireblocks' Signer. Uses the RAW operation mode under
@@ -86,64 +583,343 @@ impl<M: Middleware> Middleware for FireblocksMiddleware<M> {
     async fn sign<T: Into<Bytes> + Send + Sync>(
         &self,
         data: T,
-        _: &Address,
+        from: &Address,
     ) -> Result<Signature, Self::Error> {
-        Ok(self.fireblocks.sign_message(data.into()).await?)
+        let signer = self.signer_for(Some(from));
+        check_sender(Some(from), signer.address())?;
+        Ok(signer.sign_message(data.into()).await?)
     }
 }
 
 impl FireblocksSigner {
-    /// Submits a transaction with the Fireblocks `CONTRACT_CALL` mode, using the provided
-    /// note.
+    /// Submits a transaction with the Fireblocks `CONTRACT_CALL` (or `TRANSFER`) mode, using
+    /// the provided note.
     pub async fn submit_transaction<T: Into<TypedTransaction> + Send + Sync>(
         &self,
         tx: T,
         note: String,
     ) -> Result<TxHash, FireblocksError> {
+        let options = TransactionOptions {
+            note: (!note.is_empty()).then_some(note),
+            ..Default::default()
+        };
+        self.submit_transaction_with_options(tx, options).await
+    }
+
+    /// Submits a transaction with the Fireblocks `CONTRACT_CALL` (or `TRANSFER`) mode, using
+    /// the provided note and `externalTxId`. Fireblocks rejects a second transaction with the
+    /// same `externalTxId`, so retrying a submission (e.g. after a network error) cannot
+    /// create a duplicate transaction. With an [intent store](FireblocksSigner::intent_store),
+    /// the retry resolves to the original transaction instead of failing.
+    pub async fn submit_transaction_with_external_id<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        note: String,
+        external_tx_id: &str,
+    ) -> Result<TxHash, FireblocksError> {
+        let options = TransactionOptions {
+            note: (!note.is_empty()).then_some(note),
+            external_tx_id: Some(external_tx_id.to_owned()),
+            ..Default::default()
+        };
+        self.submit_transaction_with_options(tx, options).await
+    }
+
+    /// Submits a transaction with the Fireblocks `CONTRACT_CALL` (or `TRANSFER`) mode, using
+    /// the provided options.
+    pub async fn submit_transaction_with_options<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        options: TransactionOptions,
+    ) -> Result<TxHash, FireblocksError> {
+        Ok(self.submit_transaction_with_details(tx, options).await?.0)
+    }
+
+    /// Submits a transaction with the provided options, returning its hash along with the
+    /// details of the Fireblocks transaction, e.g. its id
+    pub async fn submit_transaction_with_details<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        options: TransactionOptions,
+    ) -> Result<(TxHash, TransactionDetails), FireblocksError> {
+        let tx = tx.into();
+        let args = self.transaction_arguments(&tx, &options)?;
+        self.submit_arguments(args, &options).await
+    }
+
+    /// Submits a transaction with the provided options and returns as soon as Fireblocks
+    /// created it, without waiting for it to be approved or broadcast. The returned id can be
+    /// persisted and awaited later with [`wait_for_completion`](Self::wait_for_completion).
+    pub async fn submit_transaction_nowait<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        options: TransactionOptions,
+    ) -> Result<CreateTransactionResponse, FireblocksError> {
         let tx = tx.into();
-        let gas_price = match tx {
-            TypedTransaction::Eip2930(ref inner) => inner.tx.gas_price,
-            TypedTransaction::Legacy(ref tx) => tx.gas_price,
-            TypedTransaction::Eip1559(ref tx) => tx.max_fee_per_gas,
+        let args = self.transaction_arguments(&tx, &options)?;
+        if let Some(ref destination) = args.destination {
+            self.check_whitelisted(destination, &args.asset_id).await?;
+        }
+        let _permit = match self.queue {
+            Some(ref queue) => Some(queue.acquire(options.priority).await),
+            None => None,
         };
-        let args = TransactionArguments {
-            operation: TransactionOperation::CONTRACT_CALL,
+        self.create(args).await
+    }
+
+    /// Waits for the Fireblocks transaction `tx_id` to be broadcast and returns its hash, like
+    /// [`submit_transaction`](Self::submit_transaction) does for the transactions it submits.
+    /// Lets a process re-attach to the transactions it submitted before a restart.
+    pub async fn resume_transaction(&self, tx_id: &str) -> Result<TxHash, FireblocksError> {
+        let (tx_hash, _) = self
+            .wait_for(
+                tx_id,
+                TransactionStatus::SUBMITTED,
+                &TransactionOptions::default(),
+                self.timeouts(),
+                with_tx_hash,
+            )
+            .await?;
+        Ok(tx_hash)
+    }
+
+    /// Waits for the Fireblocks transaction `tx_id` to be COMPLETED, returning its details,
+    /// regardless of the signer's [terminal statuses](Self::terminal_statuses). Fails if the
+    /// transaction gets rejected, cancelled or fails, with a
+    /// [`Timeout`](FireblocksError::Timeout) if it is still pending approval once `deadline`
+    /// elapses, and with a [`BroadcastTimeout`](FireblocksError::BroadcastTimeout) if it has
+    /// not completed within `deadline` of being approved.
+    pub async fn wait_for_completion(
+        &self,
+        tx_id: &str,
+        deadline: std::time::Duration,
+    ) -> Result<TransactionDetails, FireblocksError> {
+        let options = TransactionOptions {
+            wait_until_completed: true,
+            terminal_statuses: Some(TerminalStatuses::default()),
+            ..Default::default()
+        };
+        // every transaction starts out as SUBMITTED, the actual status is polled right away
+        self.wait_for(
+            tx_id,
+            TransactionStatus::SUBMITTED,
+            &options,
+            (deadline, deadline),
+            Ok,
+        )
+        .await
+    }
+
+    /// Transfers `amount` (in the token's base units) of the ERC-20 token with the Fireblocks
+    /// asset id `asset_id` to `to`. The transfer is submitted with the `TRANSFER` operation, so
+    /// approvers see the token and amount being sent instead of the calldata of a call to the
+    /// token contract.
+    pub async fn transfer_erc20(
+        &self,
+        asset_id: &str,
+        to: Address,
+        amount: U256,
+        decimals: u32,
+    ) -> Result<TxHash, FireblocksError> {
+        self.transfer_erc20_with_options(asset_id, to, amount, decimals, Default::default())
+            .await
+    }
+
+    /// Transfers `amount` of the ERC-20 token `asset_id` to `to`, using the provided options.
+    /// See [`transfer_erc20`](Self::transfer_erc20).
+    pub async fn transfer_erc20_with_options(
+        &self,
+        asset_id: &str,
+        to: Address,
+        amount: U256,
+        decimals: u32,
+        options: TransactionOptions,
+    ) -> Result<TxHash, FireblocksError> {
+        let args = self.erc20_arguments(asset_id, to, amount, decimals, &options)?;
+        Ok(self.submit_arguments(args, &options).await?.0)
+    }
+
+    async fn submit_arguments(
+        &self,
+        args: TransactionArguments,
+        options: &TransactionOptions,
+    ) -> Result<(TxHash, TransactionDetails), FireblocksError> {
+        if let Some(ref destination) = args.destination {
+            self.check_whitelisted(destination, &args.asset_id).await?;
+        }
+
+        self.handle_action(args, options, with_tx_hash).await
+    }
+
+    fn erc20_arguments(
+        &self,
+        asset_id: &str,
+        to: Address,
+        amount: U256,
+        decimals: u32,
+        options: &TransactionOptions,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        let tx = TransactionRequest::new().to(to).into();
+        let mut args = self.transaction_arguments(&tx, options)?;
+        args.operation = TransactionOperation::TRANSFER;
+        args.asset_id = asset_id.to_owned();
+        args.amount = format_amount(amount, decimals)?;
+        Ok(args)
+    }
+
+    /// Returns the arguments which [`submit_transaction`](Self::submit_transaction) would post
+    /// to Fireblocks for `tx`, without posting them. Destinations are not checked against the
+    /// whitelist, and generated `externalTxId`s differ from the ones of the actual submission.
+    pub fn preview_transaction<T: Into<TypedTransaction>>(
+        &self,
+        tx: T,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        self.preview_transaction_with_options(tx, &TransactionOptions::default())
+    }
+
+    /// Returns the arguments which
+    /// [`submit_transaction_with_options`](Self::submit_transaction_with_options) would post
+    /// to Fireblocks for `tx`, without posting them
+    pub fn preview_transaction_with_options<T: Into<TypedTransaction>>(
+        &self,
+        tx: T,
+        options: &TransactionOptions,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        self.transaction_arguments(&tx.into(), options)
+    }
+
+    fn transaction_arguments(
+        &self,
+        tx: &TypedTransaction,
+        options: &TransactionOptions,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        check_sender(tx.from(), self.address)?;
+        let fee_level = options.fee_level.or(self.fee_level);
+        // a fee level replaces the explicit prices, which are most likely estimates
+        let (gas_price, max_fee, priority_fee) = match fee_level {
+            Some(_) => (None, None, None),
+            None => fee_params(tx),
+        };
+        let mut note = options
+            .note
+            .clone()
+            .or_else(|| self.default_note.as_ref().map(|f| f.generate(tx)))
+            .unwrap_or_default();
+        let abi = options.abi.as_ref().or_else(|| {
+            tx.to()
+                .and_then(|to| to.as_address())
+                .and_then(|to| self.abis.get(to))
+        });
+        if let (Some(abi), Some(data)) = (abi, tx.data()) {
+            if let Some(call) = decode_call(abi, data) {
+                if !note.is_empty() {
+                    note.push('\n');
+                }
+                note.push_str(&call);
+            }
+        }
+        if let (Some(url), Some(data)) = (&self.calldata_explorer, tx.data()) {
+            if !data.is_empty() {
+                if !note.is_empty() {
+                    note.push('\n');
+                }
+                note.push_str(&format!("Decode calldata: {}", calldata_link(url, data)));
+            }
+        }
+        let mut destination = self.to_destination(tx.to());
+        if let Some(ref tag) = options.destination_tag {
+            check_destination_tag(tag)?;
+            let ota = destination
+                .as_mut()
+                .and_then(|destination| destination.one_time_address.as_mut())
+                .ok_or_else(|| FireblocksError::InvalidDestinationTag {
+                    tag: tag.clone(),
+                    reason: "the transaction has no destination address",
+                })?;
+            ota.tag = Some(tag.clone());
+        }
+        let data = tx.data().filter(|data| !data.is_empty());
+        // plain value transfers go through transfer policies instead of contract call ones,
+        // while deployments are contract calls without a destination
+        let operation = if data.is_none() && tx.to().is_some() && !self.force_contract_call {
+            TransactionOperation::TRANSFER
+        } else {
+            TransactionOperation::CONTRACT_CALL
+        };
+        Ok(TransactionArguments {
+            operation,
             source: TransferPeerPath {
                 peer_type: Some(PeerType::VAULT_ACCOUNT),
                 id: Some(self.account_id.clone()),
             },
-            destination: self.to_destination(tx.to()),
-            extra_parameters: tx
-                .data()
+            destination,
+            extra_parameters: data
                 .map(|data| ExtraParameters::ContractCallData(data.0.to_hex::<String>())),
 
             // rest is unnecessary
             asset_id: self.asset_id.clone(),
-            amount: tx.value().cloned().unwrap_or_default().to_string(),
+            amount: format_amount(tx.value().cloned().unwrap_or_default(), NATIVE_DECIMALS)?,
             gas_price: gas_price.map(|x| x.to_string()),
             gas_limit: tx.gas().map(|x| x.to_string()),
-            note,
+            fee_level,
+            max_fee: max_fee.map(|x| x.to_string()),
+            priority_fee: priority_fee.map(|x| x.to_string()),
+            note: self.fireblocks.environment().tag_note(note),
+            customer_ref_id: options.customer_ref_id.clone(),
+            external_tx_id: options
+                .external_tx_id
+                .clone()
+                .or_else(|| self.default_external_tx_id.as_ref().map(|f| f.generate(tx))),
+            replace_tx_by_hash: options.replace_tx_by_hash.map(|hash| format!("{:?}", hash)),
+            fail_on_low_fee: options.fail_on_low_fee,
+            force_sweep: options.force_sweep,
+            treat_as_gross_amount: options.treat_as_gross_amount,
+        })
+    }
+
+    async fn check_whitelisted(
+        &self,
+        destination: &DestinationTransferPeerPath,
+        asset_id: &str,
+    ) -> Result<(), FireblocksError> {
+        let wallet_id = match destination {
+            DestinationTransferPeerPath {
+                peer_type: PeerType::EXTERNAL_WALLET,
+                id: Some(id),
+                ..
+            } if self.verify_whitelist => id,
+            _ => return Ok(()),
         };
 
-        self.handle_action(args, |details| {
-            details.tx_hash[2..]
-                .parse::<TxHash>()
-                .map_err(|err| FireblocksError::ParseError(err.to_string()))
-        })
-        .await
+        let wallet = self.fireblocks.external_wallet(wallet_id).await?;
+        let status = wallet
+            .assets
+            .iter()
+            .find(|asset| asset.id == asset_id)
+            .map(|asset| asset.status);
+        if status != Some(WalletAssetStatus::APPROVED) {
+            return Err(FireblocksError::WalletNotApproved {
+                wallet_id: wallet_id.clone(),
+                asset_id: asset_id.to_owned(),
+                status,
+            });
+        }
+        Ok(())
     }
 
     fn to_destination(&self, to: Option<&NameOrAddress>) -> Option<DestinationTransferPeerPath> {
         match to {
             Some(NameOrAddress::Address(addr)) => {
-                let ota = OneTimeAddress {
-                    address: format!("{:?}", addr),
-                    tag: None,
+                let address = if self.checksum_addresses {
+                    to_checksum(addr, None)
+                } else {
+                    format!("{:?}", addr)
                 };
+                let ota = OneTimeAddress { address, tag: None };
 
-                Some(if let Some(id) = self.account_ids.get(addr) {
+                Some(if let Some((peer_type, id)) = self.account_ids.get(addr) {
                     DestinationTransferPeerPath {
-                        peer_type: PeerType::EXTERNAL_WALLET,
+                        peer_type: peer_type.clone(),
                         id: Some(id.clone()),
                         one_time_address: Some(ota),
                     }
@@ -160,14 +936,175 @@ impl FireblocksSigner {
     }
 }
 
+/// Pairs a broadcast transaction's details with its parsed hash
+fn with_tx_hash(
+    details: TransactionDetails,
+) -> Result<(TxHash, TransactionDetails), FireblocksError> {
+    let tx_hash = details
+        .tx_hash
+        .trim_start_matches("0x")
+        .parse::<TxHash>()
+        .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
+    Ok((tx_hash, details))
+}
+
+/// The longest destination tag or memo, in bytes, which is accepted. Memo-based chains
+/// supported by Fireblocks cap memos at 256 bytes (e.g. EOS and Cosmos SDK chains).
+pub const MAX_DESTINATION_TAG_LENGTH: usize = 256;
+
+/// Rejects destination tags which no chain accepts, instead of letting Fireblocks fail (or
+/// worse, strip) them
+fn check_destination_tag(tag: &str) -> Result<(), FireblocksError> {
+    let reason = if tag.trim().is_empty() {
+        "the tag is empty"
+    } else if tag.len() > MAX_DESTINATION_TAG_LENGTH {
+        "the tag is longer than 256 bytes"
+    } else if tag.chars().any(char::is_control) {
+        "the tag contains control characters"
+    } else {
+        return Ok(());
+    };
+    Err(FireblocksError::InvalidDestinationTag {
+        tag: tag.to_owned(),
+        reason,
+    })
+}
+
+/// Rejects transactions which are explicitly sent from another address than the vault's,
+/// since Fireblocks would silently send them from the vault instead
+fn check_sender(from: Option<&Address>, address: Address) -> Result<(), FireblocksError> {
+    match from {
+        Some(from) if *from != address => Err(FireblocksError::SenderMismatch {
+            expected: address,
+            got: *from,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Decodes calldata into a readable call, e.g. `transfer(to: 0x…, amount: 5)`. Returns `None` if
+/// the ABI has no function with the calldata's selector or the arguments do not decode.
+fn decode_call(abi: &Abi, data: &Bytes) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
+    let (selector, input) = data.split_at(4);
+    let function = abi
+        .functions()
+        .find(|function| function.short_signature() == selector)?;
+    let tokens = function.decode_input(input).ok()?;
+    let args = function
+        .inputs
+        .iter()
+        .zip(tokens)
+        .map(|(param, token)| {
+            let value = format_token(token);
+            if param.name.is_empty() {
+                value
+            } else {
+                format!("{}: {}", param.name, value)
+            }
+        })
+        .collect::<Vec<_>>();
+    Some(format!("{}({})", function.name, args.join(", ")))
+}
+
+// Formats a decoded argument for humans: addresses are checksummed and integers are printed
+// in decimal, unlike ethabi's `Display` which prints them in hex
+fn format_token(token: Token) -> String {
+    let join = |tokens: Vec<Token>| {
+        tokens
+            .into_iter()
+            .map(format_token)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match token {
+        Token::Address(address) => to_checksum(&address, None),
+        Token::Uint(value) => value.to_string(),
+        // ints are two's complement encoded
+        Token::Int(value) if value.bit(255) => format!("-{}", (!value).overflowing_add(1.into()).0),
+        Token::Int(value) => value.to_string(),
+        Token::Array(tokens) | Token::FixedArray(tokens) => format!("[{}]", join(tokens)),
+        Token::Tuple(tokens) => format!("({})", join(tokens)),
+        token => token.to_string(),
+    }
+}
+
+/// The decimals of the native asset of every supported chain
+const NATIVE_DECIMALS: u32 = 18;
+
+/// Formats a value in the asset's base units (e.g. wei) as an amount in the asset's units (e.g.
+/// ETH), which is what Fireblocks expects
+fn format_amount(value: U256, decimals: u32) -> Result<String, FireblocksError> {
+    let amount = format_units(value, decimals)
+        .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
+    Ok(if amount.contains('.') {
+        amount
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    } else {
+        amount
+    })
+}
+
+/// Links the calldata in the provided explorer url
+fn calldata_link(url: &str, data: &Bytes) -> String {
+    let calldata = format!("0x{}", data.0.to_hex::<String>());
+    if url.contains("{calldata}") {
+        url.replace("{calldata}", &calldata)
+    } else {
+        format!("{}{}", url, calldata)
+    }
+}
+
+/// Returns the `(gasPrice, maxFee, priorityFee)` triple to send to Fireblocks. EIP-1559
+/// transactions which carry a priority fee are sent with `maxFee` and `priorityFee`, so that
+/// they are broadcast as type 2 transactions with the intended tip instead of treating the
+/// max fee as a legacy gas price.
+fn fee_params(tx: &TypedTransaction) -> (Option<U256>, Option<U256>, Option<U256>) {
+    match tx {
+        TypedTransaction::Eip2930(ref inner) => (inner.tx.gas_price, None, None),
+        TypedTransaction::Legacy(ref tx) => (tx.gas_price, None, None),
+        TypedTransaction::Eip1559(ref tx) => match tx.max_priority_fee_per_gas {
+            Some(priority_fee) => (None, tx.max_fee_per_gas, Some(priority_fee)),
+            // without a tip, the transaction can only be priced as a legacy one
+            None => (tx.max_fee_per_gas, None, None),
+        },
+    }
+}
+
+/// Parses a hex address, validating its EIP-55 checksum if it is mixed-case. All-lowercase
+/// and all-uppercase addresses carry no checksum and are accepted as-is.
+pub(crate) fn parse_address(address: &str) -> Result<Address, FireblocksError> {
+    let hex = address.strip_prefix("0x").unwrap_or(address);
+    let parsed = hex
+        .parse::<Address>()
+        .map_err(|err| FireblocksError::ParseError(format!("{}: {}", address, err)))?;
+
+    let is_mixed_case =
+        hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if is_mixed_case && to_checksum(&parsed, None)[2..] != *hex {
+        return Err(FireblocksError::ParseError(format!(
+            "invalid EIP-55 checksum for address {}",
+            address
+        )));
+    }
+    Ok(parsed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_signer;
-    use ethers_core::types::TransactionRequest;
+    use crate::{
+        mock_api::{self, MockApi},
+        test_signer,
+    };
+    use ethers_core::types::{Eip1559TransactionRequest, TransactionReceipt, TransactionRequest};
     use ethers_providers::Provider;
     use rustc_hex::FromHex;
-    use std::convert::TryFrom;
+    use std::{convert::TryFrom, time::Duration};
 
     #[tokio::test]
     async fn broadcasts_tx() {
@@ -189,4 +1126,630 @@ mod tests {
         let receipt = pending_tx.await.unwrap().unwrap();
         assert_eq!(receipt.transaction_hash, tx_hash);
     }
+
+    #[tokio::test]
+    async fn deploys_contracts_through_contract_calls() {
+        let (provider, mock) = Provider::mocked();
+        // the gas estimate
+        mock.push(U256::from(53_000)).unwrap();
+        let client = FireblocksMiddleware::new(provider, offline_signer());
+
+        // a contract whose code returns 42
+        let init_code = "600a600c600039600a6000f3602a60805260206080f3";
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .data(init_code.from_hex::<Vec<u8>>().unwrap())
+            .gas_price(1)
+            .into();
+        let args = client
+            .send_arguments(&mut tx, None, &TransactionOptions::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            args.operation,
+            TransactionOperation::CONTRACT_CALL
+        ));
+        assert!(args.destination.is_none());
+        assert!(matches!(
+            args.extra_parameters,
+            Some(ExtraParameters::ContractCallData(ref data)) if data == init_code
+        ));
+        assert_eq!(args.gas_limit.as_deref(), Some("53000"));
+        assert_eq!(args.source.id.as_deref(), Some("0"));
+
+        // the deployment goes through Fireblocks, and its receipt through the provider
+        let hash = TxHash::repeat_byte(0xab);
+        let vault = Address::from_low_u64_be(1);
+        let deployed = ethers_core::utils::get_contract_address(vault, 0);
+        let mock = MockApi::serve(vec![
+            serde_json::json!({ "id": "tx", "status": "SUBMITTED" }),
+            mock_api::details("BROADCASTING", &format!("{:?}", hash)),
+        ]);
+        let (provider, rpc) = Provider::mocked();
+        let provider = provider.interval(Duration::from_millis(1));
+        // responses are popped from the back
+        rpc.push(TransactionReceipt {
+            transaction_hash: hash,
+            from: vault,
+            contract_address: Some(deployed),
+            block_number: Some(1.into()),
+            status: Some(1.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        rpc.push(Transaction {
+            hash,
+            from: vault,
+            block_number: Some(1.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        rpc.push(U256::from(53_000)).unwrap();
+        let client = FireblocksMiddleware::new(provider, mock.signer_at(vault));
+
+        let tx = TransactionRequest::new()
+            .data(init_code.from_hex::<Vec<u8>>().unwrap())
+            .gas_price(1);
+        let pending = client.send_transaction(tx, None).await.unwrap();
+        assert_eq!(pending.tx_hash(), hash);
+        let receipt = pending.await.unwrap().unwrap();
+        assert_eq!(receipt.transaction_hash, hash);
+        assert_eq!(receipt.contract_address, Some(deployed));
+
+        assert_eq!(mock.requests()[0], "POST /v1/transactions");
+        let body = &mock.bodies()[0];
+        assert_eq!(body["operation"], "CONTRACT_CALL");
+        assert!(body.get("destination").is_none());
+        assert_eq!(body["extraParameters"]["contractCallData"], init_code);
+        assert_eq!(body["gasLimit"], "53000");
+    }
+
+    #[tokio::test]
+    async fn returns_once_broadcast() {
+        let hash = format!("{:?}", TxHash::repeat_byte(0xab));
+        let tx = || {
+            TransactionRequest::new()
+                .to(Address::from_low_u64_be(2))
+                .value(1)
+        };
+        let created = serde_json::json!({ "id": "tx", "status": "SUBMITTED" });
+
+        let mock = MockApi::serve(vec![
+            created.clone(),
+            mock_api::details("PENDING_SIGNATURE", ""),
+            // the hash is not known yet
+            mock_api::details("BROADCASTING", ""),
+            mock_api::details("BROADCASTING", &hash),
+        ]);
+        let (tx_hash, details) = mock
+            .signer()
+            .submit_transaction_with_details(tx(), TransactionOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(format!("{:?}", tx_hash), hash);
+        assert_eq!(details.status, TransactionStatus::BROADCASTING);
+        assert_eq!(mock.requests()[0], "POST /v1/transactions");
+        assert_eq!(mock.requests().len(), 4);
+
+        let mock = MockApi::serve(vec![
+            created,
+            mock_api::details("BROADCASTING", &hash),
+            mock_api::details("CONFIRMING", &hash),
+            mock_api::details("COMPLETED", &hash),
+        ]);
+        let options = TransactionOptions {
+            wait_until_completed: true,
+            ..Default::default()
+        };
+        let (_, details) = mock
+            .signer()
+            .submit_transaction_with_details(tx(), options)
+            .await
+            .unwrap();
+        assert_eq!(details.status, TransactionStatus::COMPLETED);
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn submits_without_waiting() {
+        let hash = format!("{:?}", TxHash::repeat_byte(0xab));
+        let mock = MockApi::serve(vec![
+            serde_json::json!({ "id": "tx", "status": "SUBMITTED" }),
+            mock_api::details("BROADCASTING", &hash),
+            mock_api::details("COMPLETED", &hash),
+        ]);
+        let signer = mock.signer();
+        let tx = TransactionRequest::new()
+            .to(Address::from_low_u64_be(2))
+            .value(1);
+
+        let created = signer
+            .submit_transaction_nowait(tx, TransactionOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(created.id, "tx");
+        assert_eq!(mock.requests(), ["POST /v1/transactions"]);
+
+        // waits past the broadcast, for the completion
+        let details = signer
+            .wait_for_completion(&created.id, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(details.status, TransactionStatus::COMPLETED);
+        assert_eq!(mock.requests().len(), 3);
+
+        // the signer's own terminal statuses do not cut the wait short
+        let mock = MockApi::serve(vec![
+            mock_api::details("CONFIRMING", &hash),
+            mock_api::details("COMPLETED", &hash),
+        ]);
+        let mut signer = mock.signer();
+        signer.terminal_statuses(TerminalStatuses {
+            success: vec![TransactionStatus::CONFIRMING],
+            failure: vec![],
+        });
+        let details = signer
+            .wait_for_completion("tx", Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(details.status, TransactionStatus::COMPLETED);
+
+        let mock = MockApi::serve(vec![mock_api::details("PENDING_SIGNATURE", ""); 1000]);
+        assert!(matches!(
+            mock.signer()
+                .wait_for_completion("tx", Duration::from_millis(20))
+                .await,
+            Err(FireblocksError::Timeout { .. })
+        ));
+    }
+
+    #[test]
+    fn links_calldata() {
+        let data = Bytes::from(vec![0xea, 0xd7, 0x10, 0xc4]);
+        assert_eq!(
+            calldata_link("https://decoder.local/?data={calldata}&chain=5", &data),
+            "https://decoder.local/?data=0xead710c4&chain=5"
+        );
+        assert_eq!(
+            calldata_link("https://decoder.local/", &data),
+            "https://decoder.local/0xead710c4"
+        );
+    }
+
+    fn offline_signer() -> FireblocksSigner {
+        let client = FireblocksClient::new(jsonwebtoken::EncodingKey::from_secret(b""), "key");
+        let vault = Address::from_low_u64_be(1);
+        FireblocksSigner::with_client(client, "0".to_owned(), 1, "ETH".to_owned(), vault)
+    }
+
+    #[tokio::test]
+    async fn fills_gas_from_the_vault() {
+        let (provider, mock) = Provider::mocked();
+        // responses are popped from the back
+        mock.push(U256::from(50_000)).unwrap();
+        mock.push(U256::from(7)).unwrap();
+        let client = FireblocksMiddleware::new(provider, offline_signer());
+
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::from_low_u64_be(2))
+            .into();
+        client.fill_transaction(&mut tx, None).await.unwrap();
+        assert_eq!(tx.from(), Some(&Address::from_low_u64_be(1)));
+        assert_eq!(tx.gas_price(), Some(U256::from(7)));
+        assert_eq!(tx.gas(), Some(&U256::from(50_000)));
+    }
+
+    #[tokio::test]
+    async fn checks_the_chain_id() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(1)).unwrap();
+        assert!(
+            FireblocksMiddleware::new_checked(provider, offline_signer())
+                .await
+                .is_ok()
+        );
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(5)).unwrap();
+        assert!(matches!(
+            FireblocksMiddleware::new_checked(provider, offline_signer()).await,
+            Err(FireblocksMiddlewareError::FireblocksError(
+                FireblocksError::ChainIdMismatch {
+                    signer: 1,
+                    provider: 5
+                }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn tracks_nonces() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(5)).unwrap();
+        let mut client = FireblocksMiddleware::new(provider, offline_signer());
+        client.manage_nonces(true);
+
+        for expected in [5, 6] {
+            let mut tx: TypedTransaction = TransactionRequest::new()
+                .to(Address::from_low_u64_be(2))
+                .gas(21_000)
+                .gas_price(1)
+                .into();
+            client.fill_transaction(&mut tx, None).await.unwrap();
+            assert_eq!(tx.nonce(), Some(&U256::from(expected)));
+        }
+        mock.assert_request(
+            "eth_getTransactionCount",
+            (Address::from_low_u64_be(1), "pending"),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn tracks_nonces_of_concurrent_fills() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(5)).unwrap();
+        let mut client = FireblocksMiddleware::new(provider, offline_signer());
+        client.manage_nonces(true);
+
+        let fill = || async {
+            let mut tx: TypedTransaction = TransactionRequest::new()
+                .to(Address::from_low_u64_be(2))
+                .gas(21_000)
+                .gas_price(1)
+                .into();
+            client.fill_transaction(&mut tx, None).await.unwrap();
+            *tx.nonce().unwrap()
+        };
+        let (a, b) = futures_util::future::join(fill(), fill()).await;
+        let mut nonces = [a, b];
+        nonces.sort();
+        assert_eq!(nonces, [U256::from(5), U256::from(6)]);
+
+        // an unused nonce is given back by re-syncing with the pending nonce
+        client
+            .release_nonce(Address::from_low_u64_be(1), U256::from(6))
+            .await;
+        mock.push(U256::from(6)).unwrap();
+        assert_eq!(fill().await, U256::from(6));
+    }
+
+    #[tokio::test]
+    async fn releases_the_nonces_of_failed_signatures() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(5)).unwrap();
+        let api = MockApi::serve(vec![
+            serde_json::json!({ "id": "tx", "status": "SUBMITTED" }),
+            mock_api::details("REJECTED", ""),
+        ]);
+        let mut client = FireblocksMiddleware::new(provider, api.signer());
+        client.manage_nonces(true);
+        let vault = Address::from_low_u64_be(1);
+
+        let fill = |from| {
+            let client = &client;
+            async move {
+                let mut tx: TypedTransaction = TransactionRequest::new()
+                    .from(from)
+                    .to(Address::from_low_u64_be(2))
+                    .gas(21_000)
+                    .gas_price(1)
+                    .into();
+                client.fill_transaction(&mut tx, None).await.map(|_| tx)
+            }
+        };
+        let tx = fill(vault).await.unwrap();
+        assert_eq!(tx.nonce(), Some(&U256::from(5)));
+        assert!(client.sign_transaction(&tx, vault).await.is_err());
+        assert_eq!(api.requests()[0], "POST /v1/transactions");
+
+        // the rejected nonce is used by the next transaction instead of leaving a gap
+        mock.push(U256::from(5)).unwrap();
+        let tx = fill(vault).await.unwrap();
+        assert_eq!(tx.nonce(), Some(&U256::from(5)));
+
+        // unknown senders are refused before a nonce is drawn
+        let unknown = Address::from_low_u64_be(3);
+        assert!(matches!(
+            fill(unknown).await,
+            Err(FireblocksMiddlewareError::FireblocksError(
+                FireblocksError::SenderMismatch { .. }
+            ))
+        ));
+        assert_eq!(fill(vault).await.unwrap().nonce(), Some(&U256::from(6)));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_sign_for_unknown_senders() {
+        let (provider, _) = Provider::mocked();
+        let client = FireblocksMiddleware::new(provider, offline_signer());
+        let unknown = Address::from_low_u64_be(3);
+        assert!(matches!(
+            client.sign(vec![1, 2, 3], &unknown).await,
+            Err(FireblocksMiddlewareError::FireblocksError(
+                FireblocksError::SenderMismatch { got, .. }
+            )) if got == unknown
+        ));
+    }
+
+    #[tokio::test]
+    async fn routes_by_sender() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(9)).unwrap();
+        mock.push(U256::from(5)).unwrap();
+        let mut client = FireblocksMiddleware::new(provider, offline_signer());
+        client.manage_nonces(true);
+        let other = Address::from_low_u64_be(3);
+        let api = FireblocksClient::new(jsonwebtoken::EncodingKey::from_secret(b""), "key");
+        client.add_vault(FireblocksSigner::with_client(
+            api,
+            "1".to_owned(),
+            1,
+            "ETH".to_owned(),
+            other,
+        ));
+
+        assert_eq!(client.signer_for(None).account_id, "0");
+        assert_eq!(client.signer_for(Some(&other)).account_id, "1");
+        let args = client
+            .signer_for(Some(&other))
+            .preview_transaction(TransactionRequest::new().from(other).to(other))
+            .unwrap();
+        assert_eq!(args.source.id.as_deref(), Some("1"));
+
+        // each vault has its own nonce
+        for (from, expected) in [(None, 5), (Some(other), 9), (None, 6)] {
+            let mut tx = TransactionRequest::new()
+                .to(Address::from_low_u64_be(2))
+                .gas(21_000)
+                .gas_price(1);
+            tx.from = from;
+            let mut tx: TypedTransaction = tx.into();
+            client.fill_transaction(&mut tx, None).await.unwrap();
+            assert_eq!(tx.nonce(), Some(&U256::from(expected)));
+        }
+    }
+
+    #[test]
+    fn previews_contract_calls() {
+        let signer = offline_signer();
+        let tx = TransactionRequest::new()
+            .to(Address::from_low_u64_be(2))
+            .value(7)
+            .data(vec![0xab, 0xcd]);
+
+        let args = signer.preview_transaction(tx).unwrap();
+        assert!(matches!(
+            args.operation,
+            TransactionOperation::CONTRACT_CALL
+        ));
+        assert_eq!(args.source.id.as_deref(), Some("0"));
+        assert_eq!(args.amount, "0.000000000000000007");
+        assert!(matches!(
+            args.extra_parameters,
+            Some(ExtraParameters::ContractCallData(ref data)) if data == "abcd"
+        ));
+        assert!(signer
+            .preview_transaction(TransactionRequest::new().from(Address::from_low_u64_be(3)))
+            .is_err());
+
+        let transfer = TransactionRequest::new()
+            .to(Address::from_low_u64_be(2))
+            .value(7);
+        let args = signer.preview_transaction(transfer.clone()).unwrap();
+        assert!(matches!(args.operation, TransactionOperation::TRANSFER));
+        assert!(args.extra_parameters.is_none());
+        let mut signer = signer;
+        signer.force_contract_call(true);
+        let args = signer.preview_transaction(transfer).unwrap();
+        assert!(matches!(
+            args.operation,
+            TransactionOperation::CONTRACT_CALL
+        ));
+
+        let options = TransactionOptions {
+            fee_level: Some(FeeLevel::HIGH),
+            ..Default::default()
+        };
+        let tx = TransactionRequest::new().gas_price(10);
+        let args = signer
+            .preview_transaction_with_options(tx, &options)
+            .unwrap();
+        assert_eq!(args.fee_level, Some(FeeLevel::HIGH));
+        assert_eq!(args.gas_price, None);
+    }
+
+    #[test]
+    fn formats_amounts_in_asset_units() {
+        let ether = U256::exp10(18);
+        assert_eq!(format_amount(ether, NATIVE_DECIMALS).unwrap(), "1");
+        assert_eq!(
+            format_amount(ether * 1500, NATIVE_DECIMALS).unwrap(),
+            "1500"
+        );
+        assert_eq!(format_amount(ether / 4, NATIVE_DECIMALS).unwrap(), "0.25");
+        assert_eq!(
+            format_amount(U256::one(), NATIVE_DECIMALS).unwrap(),
+            "0.000000000000000001"
+        );
+        assert_eq!(format_amount(U256::zero(), NATIVE_DECIMALS).unwrap(), "0");
+        assert_eq!(format_amount(U256::from(1_234_500), 6).unwrap(), "1.2345");
+
+        let signer = offline_signer();
+        let tx = TransactionRequest::new()
+            .to(Address::from_low_u64_be(2))
+            .value(ether * 3 / 2)
+            .data(vec![0xab]);
+        assert_eq!(signer.preview_transaction(tx).unwrap().amount, "1.5");
+    }
+
+    #[test]
+    fn transfers_erc20_tokens_natively() {
+        let signer = offline_signer();
+        let to = Address::from_low_u64_be(2);
+        let amount = U256::from(1_000) * U256::exp10(6);
+        let args = signer
+            .erc20_arguments("USDC", to, amount, 6, &TransactionOptions::default())
+            .unwrap();
+        assert!(matches!(args.operation, TransactionOperation::TRANSFER));
+        assert_eq!(args.asset_id, "USDC");
+        assert_eq!(args.amount, "1000");
+        assert!(args.extra_parameters.is_none());
+        // too many decimals is an error, not a panic
+        assert!(matches!(
+            signer.erc20_arguments("USDC", to, amount, 78, &TransactionOptions::default()),
+            Err(FireblocksError::ParseError(_))
+        ));
+        let destination = args.destination.unwrap();
+        assert_eq!(
+            destination.one_time_address.unwrap().address,
+            to_checksum(&to, None)
+        );
+    }
+
+    #[test]
+    fn decodes_calls_into_the_note() {
+        let abi: Abi = serde_json::from_str(
+            r#"[{"type":"function","name":"transfer","stateMutability":"nonpayable","inputs":[{"name":"to","type":"address"},{"name":"amount","type":"uint256"}],"outputs":[{"name":"","type":"bool"}]}]"#,
+        )
+        .unwrap();
+        let token = Address::from_low_u64_be(2);
+        let to = Address::from_low_u64_be(3);
+        let data = abi
+            .function("transfer")
+            .unwrap()
+            .encode_input(&[Token::Address(to), Token::Uint(5.into())])
+            .unwrap();
+        let expected = format!("transfer(to: {}, amount: 5)", to_checksum(&to, None));
+        assert_eq!(decode_call(&abi, &data.clone().into()).unwrap(), expected);
+        assert!(decode_call(&abi, &Bytes::from(vec![0xab, 0xcd, 0xef, 0x01])).is_none());
+        // 1 USDC, which ethabi would print as f4240
+        let usdc = abi
+            .function("transfer")
+            .unwrap()
+            .encode_input(&[Token::Address(to), Token::Uint(1_000_000.into())])
+            .unwrap();
+        assert_eq!(
+            decode_call(&abi, &usdc.into()).unwrap(),
+            format!("transfer(to: {}, amount: 1000000)", to_checksum(&to, None))
+        );
+
+        let mut signer = offline_signer();
+        signer.register_abi(token, abi);
+        let tx = TransactionRequest::new().to(token).data(data);
+        let options = TransactionOptions {
+            note: Some("Pay invoice".to_owned()),
+            ..Default::default()
+        };
+        let args = signer
+            .preview_transaction_with_options(tx, &options)
+            .unwrap();
+        assert_eq!(args.note, format!("Pay invoice\n{}", expected));
+    }
+
+    #[test]
+    fn formats_call_arguments_in_decimal() {
+        assert_eq!(format_token(Token::Uint(255.into())), "255");
+        assert_eq!(format_token(Token::Int(16.into())), "16");
+        assert_eq!(format_token(Token::Int(U256::MAX)), "-1");
+        assert_eq!(format_token(Token::Int(!U256::from(99))), "-100");
+        let nested = Token::Tuple(vec![
+            Token::Array(vec![Token::Uint(10.into()), Token::Uint(4096.into())]),
+            Token::Bool(true),
+        ]);
+        assert_eq!(format_token(nested), "([10, 4096], true)");
+    }
+
+    #[test]
+    fn sets_destination_tags() {
+        let signer = offline_signer();
+        let to = Address::from_low_u64_be(2);
+        let tx = TransactionRequest::new().to(to).value(1);
+        let ota = |args: TransactionArguments| args.destination.unwrap().one_time_address.unwrap();
+        assert_eq!(
+            ota(signer.preview_transaction(tx.clone()).unwrap()).tag,
+            None
+        );
+
+        let options = TransactionOptions {
+            destination_tag: Some("12345".to_owned()),
+            ..Default::default()
+        };
+        let args = signer
+            .preview_transaction_with_options(tx, &options)
+            .unwrap();
+        assert_eq!(ota(args).tag.as_deref(), Some("12345"));
+    }
+
+    #[test]
+    fn rejects_invalid_destination_tags() {
+        let signer = offline_signer();
+        let tx = TransactionRequest::new()
+            .to(Address::from_low_u64_be(2))
+            .value(1);
+        let preview = |tx: TransactionRequest, tag: String| {
+            let options = TransactionOptions {
+                destination_tag: Some(tag),
+                ..Default::default()
+            };
+            signer.preview_transaction_with_options(tx, &options)
+        };
+        let longest = "a".repeat(MAX_DESTINATION_TAG_LENGTH);
+        assert!(preview(tx.clone(), longest.clone()).is_ok());
+        for tag in [format!("{}a", longest), " ".to_owned(), "12\n34".to_owned()] {
+            assert!(matches!(
+                preview(tx.clone(), tag),
+                Err(FireblocksError::InvalidDestinationTag { .. })
+            ));
+        }
+        // a deployment has no address to tag
+        let deployment = TransactionRequest::new().data(vec![0x60]);
+        assert!(matches!(
+            preview(deployment, "12345".to_owned()),
+            Err(FireblocksError::InvalidDestinationTag { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_foreign_sender() {
+        let vault = Address::from_low_u64_be(1);
+        assert!(check_sender(None, vault).is_ok());
+        assert!(check_sender(Some(&vault), vault).is_ok());
+        assert!(matches!(
+            check_sender(Some(&Address::from_low_u64_be(2)), vault),
+            Err(FireblocksError::SenderMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn maps_eip1559_priority_fee() {
+        let legacy = TransactionRequest::new().gas_price(10).into();
+        assert_eq!(fee_params(&legacy), (Some(10.into()), None, None));
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(100)
+            .max_priority_fee_per_gas(2)
+            .into();
+        assert_eq!(fee_params(&tx), (None, Some(100.into()), Some(2.into())));
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new().max_fee_per_gas(100).into();
+        assert_eq!(fee_params(&tx), (Some(100.into()), None, None));
+    }
+
+    #[test]
+    fn validates_address_checksums() {
+        let checksummed = "0xcBE74E21B070A979b9d6426b11e876d4cB618DaF";
+        let address = parse_address(checksummed).unwrap();
+        assert_eq!(to_checksum(&address, None), checksummed);
+
+        // no checksum to validate
+        assert_eq!(parse_address(&checksummed.to_lowercase()).unwrap(), address);
+        assert_eq!(
+            parse_address(&checksummed[2..].to_uppercase()).unwrap(),
+            address
+        );
+
+        // wrong checksum / garbage
+        assert!(parse_address("0xCBE74E21B070A979b9d6426b11e876d4cB618DaF").is_err());
+        assert!(parse_address("0xnotanaddress").is_err());
+    }
 }
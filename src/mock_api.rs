@@ -0,0 +1,170 @@
+//! A local stand-in for the Fireblocks API, which answers the requests it receives with
+//! canned responses, in order. Lets tests exercise the submission and polling logic offline.
+use crate::FireblocksClient;
+#[cfg(feature = "ethers")]
+use crate::{FireblocksSigner, PollInterval};
+#[cfg(feature = "ethers")]
+use ethers_core::types::Address;
+use jsonwebtoken::EncodingKey;
+use serde_json::Value;
+#[cfg(feature = "ethers")]
+use std::time::Duration;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// A throwaway RSA key, which only signs the JWTs of requests to the mock API
+const TEST_KEY: &[u8] = include_bytes!("testdata/test_key.pem");
+
+/// A canned response of the mock API
+pub(crate) struct MockResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Value,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// Adds a header to the response. `{url}` in `value` is replaced by the url of the mock API.
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_owned()));
+        self
+    }
+}
+
+impl From<Value> for MockResponse {
+    fn from(body: Value) -> Self {
+        Self::new(200, body)
+    }
+}
+
+pub(crate) struct MockApi {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    bodies: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockApi {
+    /// Serves each of the `responses` to one request, with a 200 unless the response says
+    /// otherwise. Further requests are refused.
+    pub fn serve<R: Into<MockResponse>>(responses: Vec<R>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let received_bodies = bodies.clone();
+        let responses: Vec<MockResponse> = responses.into_iter().map(Into::into).collect();
+        let base = url.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    match header.trim_end().split_once(':') {
+                        Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                            length = value.trim().parse().unwrap();
+                        }
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                // e.g. `GET /v1/transactions/tx`
+                let request = request_line.split_whitespace().take(2).collect::<Vec<_>>();
+                received.lock().unwrap().push(request.join(" "));
+                received_bodies
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_slice(&body).unwrap_or(Value::Null));
+
+                let headers: String = response
+                    .headers
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}\r\n", name, value.replace("{url}", &base)))
+                    .collect();
+                let body = response.body.to_string();
+                write!(
+                    reader.into_inner(),
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n{}\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.status,
+                    headers,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        Self {
+            url,
+            requests,
+            bodies,
+        }
+    }
+
+    /// A client of the mock API
+    pub fn client(&self) -> FireblocksClient {
+        let key = EncodingKey::from_rsa_pem(TEST_KEY).unwrap();
+        FireblocksClient::new_with_url(key, "key", &self.url).with_version("v1")
+    }
+
+    /// A signer for the vault account `0` of the mock API, which polls every millisecond
+    #[cfg(feature = "ethers")]
+    pub fn signer(&self) -> FireblocksSigner {
+        self.signer_at(Address::from_low_u64_be(1))
+    }
+
+    /// Like [`signer`](Self::signer), for a vault with the provided address
+    #[cfg(feature = "ethers")]
+    pub fn signer_at(&self, vault: Address) -> FireblocksSigner {
+        let mut signer = FireblocksSigner::with_client(
+            self.client(),
+            "0".to_owned(),
+            1,
+            "ETH".to_owned(),
+            vault,
+        );
+        signer.poll_interval(PollInterval::fixed(Duration::from_millis(1)));
+        signer
+    }
+
+    /// The method and path of the requests served so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The JSON bodies of the requests served so far, `null` for requests without one
+    pub fn bodies(&self) -> Vec<Value> {
+        self.bodies.lock().unwrap().clone()
+    }
+}
+
+/// The details of the transaction `tx` with the provided status, as returned by the API
+#[cfg(feature = "ethers")]
+pub(crate) fn details(status: &str, tx_hash: &str) -> Value {
+    serde_json::json!({
+        "id": "tx",
+        "assetId": "ETH",
+        "txHash": tx_hash,
+        "status": status,
+        "subStatus": "",
+        "signedMessages": [],
+    })
+}
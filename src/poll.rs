@@ -0,0 +1,437 @@
+//! Pacing of transaction status polls.
+use crate::{
+    types::{ApprovalProgress, TransactionDetails, TransactionStatus},
+    webhook::WebhookSubscription,
+    FireblocksClient, FireblocksSigner, Result,
+};
+use futures_util::stream::{self, Stream};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How often the status of a submitted transaction is polled. The delay between polls starts
+/// at `initial` and is multiplied by `factor` after every poll, up to `max`. Every delay is
+/// jittered by up to `jitter_percent` percent in either direction, so that signers which
+/// submitted at the same time do not poll in lockstep.
+pub struct PollInterval {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: u32,
+    pub jitter_percent: u32,
+}
+
+impl Default for PollInterval {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(2),
+            factor: 2,
+            jitter_percent: 10,
+        }
+    }
+}
+
+impl PollInterval {
+    /// Polls every `interval`, without backoff or jitter
+    pub fn fixed(interval: Duration) -> Self {
+        Self {
+            initial: interval,
+            max: interval,
+            factor: 1,
+            jitter_percent: 0,
+        }
+    }
+
+    pub(crate) fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: *self,
+            next: self.initial.min(self.max),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The delays between the polls of a single transaction
+pub(crate) struct Backoff {
+    policy: PollInterval,
+    next: Duration,
+}
+
+impl Backoff {
+    /// Returns the delay before the next poll
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * self.policy.factor.max(1)).min(self.policy.max);
+        jitter(delay, self.policy.jitter_percent)
+    }
+}
+
+type Hook = Arc<dyn Fn(&TransactionDetails) + Send + Sync>;
+type ProgressHook = Arc<dyn Fn(&TransactionDetails, ApprovalProgress) + Send + Sync>;
+
+#[derive(Clone, Default)]
+/// Callbacks which fire when a transaction the signer waits for reaches a status, or
+/// collects an approval
+pub(crate) struct StatusHooks {
+    status: Arc<Vec<(TransactionStatus, Hook)>>,
+    progress: Arc<Vec<ProgressHook>>,
+}
+
+impl StatusHooks {
+    pub fn add(&mut self, status: TransactionStatus, hook: Hook) {
+        Arc::make_mut(&mut self.status).push((status, hook));
+    }
+
+    pub fn add_progress(&mut self, hook: ProgressHook) {
+        Arc::make_mut(&mut self.progress).push(hook);
+    }
+
+    pub fn fire(&self, details: &TransactionDetails) {
+        self.status
+            .iter()
+            .filter(|(status, _)| *status == details.status)
+            .for_each(|(_, hook)| hook(details));
+    }
+
+    pub fn fire_progress(&self, details: &TransactionDetails, progress: ApprovalProgress) {
+        self.progress
+            .iter()
+            .for_each(|hook| hook(details, progress));
+    }
+}
+
+impl std::fmt::Debug for StatusHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "StatusHooks({})",
+            self.status.len() + self.progress.len()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The statuses at which the signer stops waiting for a transaction. Regardless of these,
+/// transactions are returned as soon as they are broadcast, unless
+/// [`wait_until_completed`](crate::TransactionOptions::wait_until_completed) is set.
+pub struct TerminalStatuses {
+    /// The statuses at which the transaction is returned
+    pub success: Vec<TransactionStatus>,
+    /// The statuses at which waiting fails with an error carrying the status
+    pub failure: Vec<TransactionStatus>,
+}
+
+impl Default for TerminalStatuses {
+    fn default() -> Self {
+        use TransactionStatus::*;
+        Self {
+            success: vec![COMPLETED],
+            failure: vec![BLOCKED, CANCELLED, REJECTED, FAILED],
+        }
+    }
+}
+
+/// Fetches the successive states of a transaction by polling, as soon as a webhook event
+/// arrives if the signer has a [`WebhookDispatcher`](crate::webhook::WebhookDispatcher), or at
+/// the poll interval otherwise
+pub(crate) struct Poller<'a> {
+    fireblocks: &'a FireblocksClient,
+    tx_id: String,
+    poll_interval: PollInterval,
+    backoff: Backoff,
+    retries: u32,
+    webhooks: Option<(WebhookSubscription, Duration)>,
+}
+
+impl<'a> Poller<'a> {
+    pub fn new(signer: &'a FireblocksSigner, tx_id: &str) -> Self {
+        let webhooks = signer.webhooks.as_ref();
+        Self {
+            fireblocks: &signer.fireblocks,
+            tx_id: tx_id.to_owned(),
+            poll_interval: signer.poll_interval,
+            backoff: signer.poll_interval.backoff(),
+            retries: signer.poll_retries,
+            webhooks: webhooks.map(|webhooks| (webhooks.subscribe(tx_id), webhooks.fallback())),
+        }
+    }
+
+    /// Returns the transaction's latest known state. Polls which fail with a
+    /// [transient](crate::FireblocksError::is_transient) error are retried.
+    pub async fn current(&mut self) -> Result<TransactionDetails> {
+        let mut backoff = self.poll_interval.backoff();
+        let mut attempt = 0;
+        loop {
+            match self.fireblocks.transaction(&self.tx_id).await {
+                Err(err) if err.is_transient() && attempt < self.retries => {
+                    attempt += 1;
+                    tokio::time::sleep(backoff.next_delay()).await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Waits until the transaction should be polled again, or until its next webhook event,
+    /// for at most `max`. Webhook bodies are not authenticated, so an event only triggers a
+    /// poll and its contents are never trusted.
+    pub async fn wait(&mut self, max: Duration) {
+        match self.webhooks {
+            Some((ref mut subscription, fallback)) => {
+                subscription.next(fallback.min(max)).await;
+            }
+            None => tokio::time::sleep(self.backoff.next_delay().min(max)).await,
+        }
+    }
+}
+
+impl FireblocksSigner {
+    /// Watches the transaction `tx_id`, yielding its details every time its status changes
+    /// (e.g. `QUEUED`, `PENDING_SIGNATURE`, `BROADCASTING`, …) or it collects an approval
+    /// (see [`approval_progress`](TransactionDetails::approval_progress)), until it reaches a
+    /// final status. The status is polled at the signer's [poll interval](Self::poll_interval), or
+    /// received through its [webhook dispatcher](Self::webhook_dispatcher). Polling errors which
+    /// are [transient](crate::FireblocksError::is_transient) are yielded without ending the
+    /// stream, any other error is yielded last.
+    pub fn watch_transaction(
+        &self,
+        tx_id: &str,
+    ) -> impl Stream<Item = Result<TransactionDetails>> + '_ {
+        let poller = Poller::new(self, tx_id);
+        let state = (poller, None, false, false);
+        stream::unfold(
+            state,
+            |(mut poller, mut last, mut waiting, failed)| async move {
+                if failed
+                    || matches!(last, Some((status, _)) if TransactionStatus::is_terminal(&status))
+                {
+                    return None;
+                }
+                loop {
+                    if waiting {
+                        poller.wait(Duration::MAX).await;
+                    }
+                    waiting = true;
+                    match poller.current().await {
+                        Ok(details)
+                            if Some((details.status, details.approval_progress())) != last =>
+                        {
+                            last = Some((details.status, details.approval_progress()));
+                            return Some((Ok(details), (poller, last, waiting, false)));
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            let failed = !err.is_transient();
+                            return Some((Err(err), (poller, last, waiting, failed)));
+                        }
+                    }
+                }
+            },
+        )
+    }
+}
+
+fn jitter(delay: Duration, percent: u32) -> Duration {
+    let spread = delay * percent.min(100) / 100;
+    if spread.is_zero() {
+        return delay;
+    }
+    // a fresh `RandomState` is randomly seeded, which is plenty for spreading polls
+    let random = RandomState::new().build_hasher().finish();
+    let offset = spread.mul_f64((random % 10_000) as f64 / 10_000.0 * 2.0);
+    delay - spread + offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock_api::{self, MockApi, MockResponse},
+        FireblocksError,
+    };
+
+    #[test]
+    fn backs_off_up_to_the_max() {
+        let policy = PollInterval {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            factor: 2,
+            jitter_percent: 0,
+        };
+        let mut backoff = policy.backoff();
+        let delays = (0..5).map(|_| backoff.next_delay()).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+
+        let mut fixed = PollInterval::fixed(Duration::from_millis(300)).backoff();
+        assert_eq!(fixed.next_delay(), Duration::from_millis(300));
+        assert_eq!(fixed.next_delay(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn fires_status_hooks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let fired = Arc::new(AtomicUsize::new(0));
+        let mut hooks = StatusHooks::default();
+        let counter = fired.clone();
+        hooks.add(
+            TransactionStatus::PENDING_SIGNATURE,
+            Arc::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        );
+        let details = |status: &str| -> TransactionDetails {
+            serde_json::from_value(serde_json::json!({
+                "id": "tx",
+                "assetId": "ETH",
+                "txHash": "",
+                "status": status,
+                "subStatus": "",
+                "signedMessages": [],
+            }))
+            .unwrap()
+        };
+
+        hooks.fire(&details("SUBMITTED"));
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+        hooks.fire(&details("PENDING_SIGNATURE"));
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn classifies_transient_errors() {
+        let api_error = |status| FireblocksError::ApiError {
+            status,
+            code: None,
+            message: String::new(),
+        };
+        assert!(api_error(502).is_transient());
+        assert!(!api_error(501).is_transient());
+        assert!(api_error(429).is_transient());
+        assert!(!api_error(400).is_transient());
+        assert!(!api_error(401).is_transient());
+        assert!(!api_error(408).is_transient());
+        assert!(!FireblocksError::Timeout { cancel_error: None }.is_transient());
+    }
+
+    #[tokio::test]
+    async fn classifies_transient_request_errors() {
+        let client = reqwest::Client::new();
+        // nothing listens on port 1
+        let refused = client.get("http://127.0.0.1:1").send().await.unwrap_err();
+        assert!(FireblocksError::ReqwestError(refused).is_transient());
+        // an invalid url fails the same way on every attempt
+        let invalid = client.get("http://[::1").send().await.unwrap_err();
+        assert!(!FireblocksError::ReqwestError(invalid).is_transient());
+    }
+
+    #[tokio::test]
+    async fn watches_status_changes() {
+        use futures_util::StreamExt;
+
+        let mock = MockApi::serve(vec![
+            mock_api::details("SUBMITTED", ""),
+            // unchanged, not yielded
+            mock_api::details("SUBMITTED", ""),
+            mock_api::details("PENDING_SIGNATURE", ""),
+            mock_api::details("BROADCASTING", "0xabc"),
+            mock_api::details("COMPLETED", "0xabc"),
+        ]);
+        let signer = mock.signer();
+        let statuses = signer
+            .watch_transaction("tx")
+            .map(|details| details.unwrap().status)
+            .collect::<Vec<_>>()
+            .await;
+        use TransactionStatus::*;
+        assert_eq!(
+            statuses,
+            vec![SUBMITTED, PENDING_SIGNATURE, BROADCASTING, COMPLETED]
+        );
+        // the stream ends at the final status instead of polling again
+        assert_eq!(mock.requests().len(), 5);
+        assert_eq!(mock.requests()[0], "GET /v1/transactions/tx");
+    }
+
+    #[tokio::test]
+    async fn stops_watching_after_permanent_errors() {
+        use futures_util::StreamExt;
+
+        let mock = MockApi::serve(vec![
+            MockResponse::from(mock_api::details("SUBMITTED", "")),
+            MockResponse::new(404, serde_json::json!({ "message": "Not found" })),
+        ]);
+        let signer = mock.signer();
+        let updates = signer.watch_transaction("tx").collect::<Vec<_>>().await;
+        assert_eq!(updates.len(), 2);
+        assert_eq!(
+            updates[0].as_ref().unwrap().status,
+            TransactionStatus::SUBMITTED
+        );
+        assert!(matches!(
+            updates[1],
+            Err(FireblocksError::ApiError { status: 404, .. })
+        ));
+        // the stream ends instead of polling the missing transaction forever
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn refetches_webhook_events() {
+        use crate::webhook::{WebhookDispatcher, WebhookEvent};
+        use futures_util::StreamExt;
+
+        let mock = MockApi::serve(vec![
+            mock_api::details("SUBMITTED", ""),
+            mock_api::details("COMPLETED", "0xabc"),
+        ]);
+        let dispatcher = WebhookDispatcher::new(Duration::from_secs(60));
+        let mut signer = mock.signer();
+        signer.webhook_dispatcher(dispatcher.clone());
+
+        let watch = signer.watch_transaction("tx");
+        futures_util::pin_mut!(watch);
+        let submitted = watch.next().await.unwrap().unwrap();
+        assert_eq!(submitted.status, TransactionStatus::SUBMITTED);
+
+        // a forged event wakes the watcher up, but the state is fetched from the API
+        let forged = WebhookEvent {
+            event_type: "TRANSACTION_STATUS_UPDATED".to_owned(),
+            tenant_id: "tenant".to_owned(),
+            timestamp: 1,
+            data: serde_json::json!({
+                "id": "tx",
+                "assetId": "ETH",
+                "txHash": "0xforged",
+                "status": "COMPLETED",
+                "subStatus": "",
+                "signedMessages": [],
+            }),
+        };
+        assert!(dispatcher.dispatch(&forged).unwrap());
+        let completed = watch.next().await.unwrap().unwrap();
+        assert_eq!(completed.status, TransactionStatus::COMPLETED);
+        assert_eq!(completed.tx_hash, "0xabc");
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[test]
+    fn jitters_within_bounds() {
+        let delay = Duration::from_secs(1);
+        for _ in 0..100 {
+            let jittered = jitter(delay, 10);
+            assert!(jittered >= Duration::from_millis(900));
+            assert!(jittered <= Duration::from_millis(1100));
+        }
+        assert_eq!(jitter(delay, 0), delay);
+    }
+}
@@ -0,0 +1,69 @@
+//! Prometheus exporter for the metrics recorded by this crate: API request, error and
+//! rate-limit counters, approval latency and in-flight transactions.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let handle = ethers_fireblocks::prometheus::install()?;
+//! let app = ethers_fireblocks::prometheus::router(handle);
+//! // serve `app` with axum, e.g. next to the rest of the service's routes
+//! # Ok(())
+//! # }
+//! ```
+use axum::{extract::State, routing::get, Router};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder};
+
+pub use metrics_exporter_prometheus::PrometheusHandle;
+
+/// Approvals are manual, so the latency buckets span seconds to an hour
+const APPROVAL_LATENCY_BUCKETS: &[f64] = &[
+    1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+
+/// Returns a builder for a Prometheus recorder with buckets suited to this crate's
+/// histograms
+pub fn builder() -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new().set_buckets_for_metric(
+        Matcher::Full("fireblocks_approval_latency_seconds".to_owned()),
+        APPROVAL_LATENCY_BUCKETS,
+    )
+}
+
+/// Installs a Prometheus recorder as the global [`metrics`] recorder and returns the
+/// handle used to render its contents
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    builder()?.install_recorder()
+}
+
+/// Renders the metrics in the Prometheus text format
+pub async fn handler(State(handle): State<PrometheusHandle>) -> String {
+    handle.render()
+}
+
+/// A router serving the metrics at `/metrics`
+pub fn router(handle: PrometheusHandle) -> Router {
+    Router::new()
+        .route("/metrics", get(handler))
+        .with_state(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{telemetry, Environment};
+
+    #[tokio::test]
+    async fn renders_metrics() {
+        let recorder = builder().unwrap().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            telemetry::rate_limited(Environment::Sandbox);
+            telemetry::approval_latency(Environment::Sandbox, std::time::Duration::from_secs(42));
+        });
+
+        let rendered = handler(State(handle)).await;
+        assert!(rendered.contains(r#"fireblocks_rate_limited_total{environment="sandbox"} 1"#));
+        assert!(rendered.contains(
+            r#"fireblocks_approval_latency_seconds_bucket{environment="sandbox",le="60"} 1"#
+        ));
+    }
+}
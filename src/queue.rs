@@ -0,0 +1,192 @@
+//! Per-vault submission queue.
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+/// The priority of a submission in the [submission queue](crate::FireblocksSigner::submission_queue).
+/// Higher priorities jump ahead of lower ones, equal priorities are served in order.
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+#[derive(Debug, Clone, Default)]
+/// Lets a single submission through at a time, in priority order. Clones share the queue.
+pub(crate) struct SubmissionQueue {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    busy: bool,
+    seq: u64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    wake: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // max-heap: highest priority first, then lowest sequence number
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Held until a submission is accepted by Fireblocks. Dropping it lets the next one through.
+#[derive(Debug)]
+pub(crate) struct QueuePermit {
+    state: Arc<Mutex<State>>,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        release(&self.state);
+    }
+}
+
+/// Hands the queue over to the next waiter which is still around, or marks it idle.
+fn release(state: &Mutex<State>) {
+    let mut state = state.lock().expect("queue lock poisoned");
+    while let Some(waiter) = state.waiters.pop() {
+        if waiter.wake.send(()).is_ok() {
+            return;
+        }
+    }
+    state.busy = false;
+}
+
+/// Makes sure that a permit which was handed to an acquire future that got dropped before
+/// observing it is passed on instead of being lost.
+struct PendingPermit {
+    rx: Option<oneshot::Receiver<()>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Drop for PendingPermit {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                release(&self.state);
+            }
+        }
+    }
+}
+
+impl SubmissionQueue {
+    /// The number of submissions waiting for their turn
+    pub fn depth(&self) -> usize {
+        let state = self.state.lock().expect("queue lock poisoned");
+        state
+            .waiters
+            .iter()
+            .filter(|waiter| !waiter.wake.is_closed())
+            .count()
+    }
+
+    /// Waits for our turn
+    pub async fn acquire(&self, priority: Priority) -> QueuePermit {
+        let rx = {
+            let mut state = self.state.lock().expect("queue lock poisoned");
+            if !state.busy {
+                state.busy = true;
+                return QueuePermit {
+                    state: self.state.clone(),
+                };
+            }
+            let (wake, rx) = oneshot::channel();
+            state.seq += 1;
+            let seq = state.seq;
+            state.waiters.push(Waiter {
+                priority,
+                seq,
+                wake,
+            });
+            rx
+        };
+
+        let mut pending = PendingPermit {
+            rx: Some(rx),
+            state: self.state.clone(),
+        };
+        let rx = pending.rx.as_mut().expect("receiver is set");
+        // the sender is only dropped without sending if the queue itself is dropped, in
+        // which case nobody else can be holding the queue either
+        let _ = rx.await;
+        pending.rx = None;
+        QueuePermit {
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn serves_higher_priority_first() {
+        let queue = SubmissionQueue::default();
+        let permit = queue.acquire(Priority::Normal).await;
+
+        let (queued_tx, mut queued) = mpsc::unbounded_channel();
+        let (served_tx, mut served) = mpsc::unbounded_channel();
+        for priority in [Priority::Low, Priority::Normal, Priority::Urgent] {
+            let queue = queue.clone();
+            let queued = queued_tx.clone();
+            let served = served_tx.clone();
+            tokio::spawn(async move {
+                let acquire = queue.acquire(priority);
+                futures_util::pin_mut!(acquire);
+                // the first poll puts the submission in line
+                assert!(futures_util::poll!(acquire.as_mut()).is_pending());
+                queued.send(()).unwrap();
+                let _permit = acquire.await;
+                served.send(priority).unwrap();
+            });
+        }
+        for _ in 0..3 {
+            queued.recv().await.unwrap();
+        }
+        assert_eq!(queue.depth(), 3);
+        drop(permit);
+
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(served.recv().await.unwrap());
+        }
+        assert_eq!(
+            order,
+            vec![Priority::Urgent, Priority::Normal, Priority::Low]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Helpers for integration testing against a Fireblocks sandbox workspace.
+use crate::{
+    api::FireblocksClient, asset_id, parse_vault_address, queue, types::CreateVaultRequest,
+    Environment, FireblocksError, FireblocksSigner, Result,
+};
+use jsonwebtoken::EncodingKey;
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+/// The chain the sandbox signer is set up for (Sepolia)
+pub const SANDBOX_CHAIN_ID: u64 = 11155111;
+
+/// Prefix of the names of the vaults created by [`FireblocksSigner::sandbox_vault`]
+const SANDBOX_VAULT_PREFIX: &str = "ethers-fireblocks-test";
+
+impl FireblocksSigner {
+    /// Instantiates a signer for the provided vault account of a sandbox workspace, on
+    /// Sepolia. Sandbox approvals tend to be manual and slow, so the timeout is raised to
+    /// 10 minutes.
+    pub async fn sandbox(key: EncodingKey, api_key: &str, account_id: &str) -> Result<Self> {
+        let fireblocks = FireblocksClient::new_with_url(key, api_key, Environment::Sandbox.url())
+            .with_environment(Environment::Sandbox);
+        let mut signer = Self::from_client(fireblocks, account_id, SANDBOX_CHAIN_ID).await?;
+        signer.timeout(Duration::from_secs(10 * 60));
+        Ok(signer)
+    }
+
+    /// Returns a signer for a hidden vault account named after `seed`, creating the vault and
+    /// its wallet if they do not exist yet. The same seed always yields the same vault, so
+    /// re-running a test suite does not pile up vaults. The signer's settings (timeout,
+    /// registered accounts, etc.) are carried over.
+    pub async fn sandbox_vault(&self, seed: &str) -> Result<Self> {
+        let name = format!("{}-{}", SANDBOX_VAULT_PREFIX, seed);
+        let existing = self
+            .fireblocks
+            .all_vaults()
+            .await?
+            .into_iter()
+            .find(|vault| vault.name == name);
+        let account_id = match existing {
+            Some(vault) => vault.id,
+            None => {
+                self.fireblocks
+                    .new_vault(CreateVaultRequest {
+                        name,
+                        hidden_on_ui: true,
+                        customer_ref_id: None,
+                        auto_fuel: false,
+                    })
+                    .await?
+                    .id
+            }
+        };
+
+        // the wallet does not exist yet for new vaults
+        let asset_id = asset_id(self.chain_id)?;
+        let addresses = match self.fireblocks.vault_addresses(&account_id, asset_id).await {
+            Ok(addresses) => addresses,
+            Err(FireblocksError::ApiError { status: 404, .. }) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let address = match addresses.first() {
+            Some(res) => res.address.clone(),
+            None => {
+                self.fireblocks
+                    .new_vault_wallet(&account_id, asset_id)
+                    .await?
+                    .address
+            }
+        };
+
+        let mut signer = self.clone();
+        signer.account_id = account_id;
+        signer.address = parse_vault_address(&address)?;
+        // pausing either signer must not pause the other
+        signer.paused = Arc::new(AtomicBool::new(self.is_paused()));
+        signer.in_flight = crate::inflight::InFlight::new(self.fireblocks.environment());
+        // the queue orders the submissions of a single vault
+        if signer.queue.is_some() {
+            signer.queue = Some(queue::SubmissionQueue::default());
+        }
+        Ok(signer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mock_api::{MockApi, MockResponse},
+        FireblocksError,
+    };
+    use ethers_core::types::Address;
+
+    fn vaults() -> serde_json::Value {
+        serde_json::json!({
+            "accounts": [{
+                "id": "7",
+                "name": "ethers-fireblocks-test-seed",
+                "hiddenOnUI": true,
+                "assets": [],
+                "customerRefId": null,
+                "autoFuel": false,
+            }],
+            "paging": {},
+            "previousUrl": null,
+            "nextUrl": null,
+        })
+    }
+
+    #[tokio::test]
+    async fn creates_missing_sandbox_wallets() {
+        let address = Address::from_low_u64_be(2);
+        let mock = MockApi::serve(vec![
+            MockResponse::from(vaults()),
+            MockResponse::new(404, serde_json::json!({ "message": "Not found" })),
+            MockResponse::from(serde_json::json!({
+                "id": "ETH",
+                "address": format!("{:?}", address),
+            })),
+        ]);
+        let signer = mock.signer().sandbox_vault("seed").await.unwrap();
+        assert_eq!(signer.account_id, "7");
+        assert_eq!(signer.address, address);
+        assert_eq!(mock.requests()[2], "POST /v1/vault/accounts/7/ETH");
+
+        // other errors are not mistaken for a missing wallet
+        let mock = MockApi::serve(vec![
+            MockResponse::from(vaults()),
+            MockResponse::new(401, serde_json::json!({ "message": "Unauthorized" })),
+        ]);
+        assert!(matches!(
+            mock.signer().sandbox_vault("seed").await,
+            Err(FireblocksError::ApiError { status: 401, .. })
+        ));
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
@@ -1,57 +1,84 @@
 use crate::{
+    asset_id,
     types::{
-        ExtraParameters, PeerType, RawMessageData, TransactionArguments, TransactionOperation,
-        TransferPeerPath, UnsignedMessage,
+        DepositAddressResponse, ExtraParameters, FeeInfo, PeerType, RawMessageData,
+        SigningAlgorithm, SupportedAsset, TransactionArguments, TransactionDetails,
+        TransactionOperation, TransactionStatus, TransferPeerPath, TypedMessageType,
+        UnsignedMessage,
     },
-    FireblocksError, FireblocksSigner,
+    FireblocksError, FireblocksSigner, TerminalStatuses, TransactionOptions,
 };
 use async_trait::async_trait;
 use ethers_core::{
-    types::{transaction::{eip2718::TypedTransaction, eip712::Eip712}, 
-        Address, Signature, H256, U256, },
-    utils::hash_message,
+    types::{
+        transaction::{
+            eip2718::TypedTransaction,
+            eip712::{Eip712, TypedData},
+        },
+        Address, NameOrAddress, Signature, H256, U256,
+    },
+    utils::{hash_message, parse_units, to_checksum},
 };
 use ethers_signers::{to_eip155_v, Signer};
-use rustc_hex::ToHex;
+use rustc_hex::{FromHex, ToHex};
+use std::{borrow::Cow, convert::TryFrom};
 
 #[async_trait]
 impl Signer for FireblocksSigner {
     type Error = FireblocksError;
 
     async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, FireblocksError> {
-        let mut tx_with_chain = tx.clone();
-        if tx_with_chain.chain_id().is_none() {
-            // in the case we don't have a chain_id, let's use the signer chain id instead
-            tx_with_chain.set_chain_id(self.chain_id);
-        }
-        let sighash = tx_with_chain.sighash();
-        self.sign(tx_with_chain, sighash, true).await
+        let (sig, _) = self.sign_transaction_with_details(tx).await?;
+        Ok(sig)
     }
 
     async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
         &self,
         message: S,
     ) -> Result<Signature, Self::Error> {
-        let hash = hash_message(&message);
-        self.sign(message.as_ref(), hash, false).await
+        let (sig, _) = self.sign_message_with_details(message).await?;
+        Ok(sig)
     }
 
-    /// Signs an EIP712 encoded domain separator and message
-    /// TODO: Implement
-    #[allow(unused_variables)]
+    /// Signs an EIP712 encoded domain separator and message. The `Eip712` trait only
+    /// exposes the digest of the payload, which can only be signed with the RAW operation,
+    /// so this fails with [`FireblocksError::TypedDataRequired`] unless
+    /// [`raw_typed_data`](FireblocksSigner::raw_typed_data) is enabled. Use
+    /// [`FireblocksSigner::sign_typed_data_message`] to sign [`TypedData`] with the
+    /// `TYPED_MESSAGE` operation instead, so that approvers see the structured data.
     async fn sign_typed_data<T: Eip712 + Send + Sync>(
         &self,
         payload: &T,
     ) -> Result<Signature, Self::Error> {
-        unimplemented!()
+        if !self.raw_typed_data {
+            return Err(FireblocksError::TypedDataRequired);
+        }
+        let hash = H256(
+            payload
+                .encode_eip712()
+                .map_err(|err| FireblocksError::ParseError(err.to_string()))?,
+        );
+        let summary = format!("Sign typed data {:?}", hash);
+        let (sig, _) = self
+            .sign(hash, RawNote::Summary(summary), hash, None, false)
+            .await?;
+        Ok(sig)
     }
 
     fn address(&self) -> Address {
         self.address
     }
 
+    /// Sets the chain id and remaps the Fireblocks asset id accordingly. The vault address is
+    /// kept as is, as it cannot be re-fetched here: use [`FireblocksSigner::switch_chain`]
+    /// for wallets whose address differs between networks, or to get an error for chains
+    /// unknown to Fireblocks. A signer switched to an unknown chain refuses to sign, failing
+    /// with [`FireblocksError::UnsupportedChainId`], rather than signing for the old chain.
     fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
         self.chain_id = chain_id.into();
+        self.asset_id = asset_id(self.chain_id)
+            .map(ToOwned::to_owned)
+            .unwrap_or_default();
         self
     }
 
@@ -60,25 +87,544 @@ impl Signer for FireblocksSigner {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The fees paid by a completed transaction, in wei
+pub struct FeeBreakdown {
+    /// The fee paid to the network (i.e. gas used * gas price)
+    pub network_fee: U256,
+    /// The fee charged by Fireblocks, if any
+    pub service_fee: U256,
+    /// The effective gas price
+    pub gas_price: Option<U256>,
+    /// The gas used, as reported by the transaction's receipt. Only known to
+    /// [`FireblocksMiddleware::fee_paid`](crate::FireblocksMiddleware::fee_paid), which has a
+    /// provider to fetch the receipt from.
+    pub gas_used: Option<U256>,
+}
+
+#[derive(Debug, Clone)]
+/// A signer which borrows a [`FireblocksSigner`], so that a shared signer can be used wherever a
+/// [`Signer`] is expected without cloning it, see [`FireblocksSigner::shared`]. The signer is
+/// only cloned when it is switched to another chain.
+pub struct SharedSigner<'a>(Cow<'a, FireblocksSigner>);
+
+impl std::ops::Deref for SharedSigner<'_> {
+    type Target = FireblocksSigner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl FireblocksSigner {
+    /// Borrows the signer as a [`Signer`]
+    pub fn shared(&self) -> SharedSigner<'_> {
+        SharedSigner(Cow::Borrowed(self))
+    }
+}
+
+#[async_trait]
+impl Signer for SharedSigner<'_> {
+    type Error = FireblocksError;
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, FireblocksError> {
+        (**self).sign_transaction(tx).await
+    }
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        (**self).sign_message(message).await
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        (**self).sign_typed_data(payload).await
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Switches a clone of the signer to `chain_id`, see [`FireblocksSigner::with_chain_id`]
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        let chain_id = chain_id.into();
+        if chain_id == self.chain_id {
+            return self;
+        }
+        SharedSigner(Cow::Owned(self.0.into_owned().with_chain_id(chain_id)))
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+}
+
+impl FeeBreakdown {
+    /// Parses the fees, which are denominated in the fee asset's units (`decimals`), and the
+    /// gas price, which is denominated in gwei
+    fn from_fee_info(info: &FeeInfo, decimals: u32) -> Result<Self, FireblocksError> {
+        let parse = |amount: &Option<String>, decimals: u32| -> Result<Option<U256>, _> {
+            amount
+                .as_deref()
+                .filter(|amount| !amount.is_empty())
+                .map(|amount| {
+                    parse_units(amount, decimals)
+                        .map(U256::from)
+                        .map_err(|err| FireblocksError::ParseError(err.to_string()))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            network_fee: parse(&info.network_fee, decimals)?.unwrap_or_default(),
+            service_fee: parse(&info.service_fee, decimals)?.unwrap_or_default(),
+            gas_price: parse(&info.gas_price, 9)?,
+            gas_used: None,
+        })
+    }
+}
+
+impl FireblocksSigner {
+    /// Fetches a completed transaction and returns a breakdown of the fees it paid, normalized
+    /// with the decimals of the asset the fees were paid in
+    pub async fn fee_paid(&self, txid: &str) -> Result<FeeBreakdown, FireblocksError> {
+        let details = self.fireblocks.transaction(txid).await?;
+        self.fees_of(&details).await
+    }
+
+    pub(crate) async fn fees_of(
+        &self,
+        details: &TransactionDetails,
+    ) -> Result<FeeBreakdown, FireblocksError> {
+        if details.status != TransactionStatus::COMPLETED {
+            return Err(FireblocksError::TxError(
+                details.status,
+                "fees are only final once the transaction is completed".to_owned(),
+            ));
+        }
+        match details.fee_info {
+            Some(ref info) => {
+                let assets = self.fireblocks.supported_assets().await?;
+                FeeBreakdown::from_fee_info(info, fee_decimals(&assets, &details.asset_id)?)
+            }
+            None => Ok(FeeBreakdown::default()),
+        }
+    }
+
+    /// Switches the signer to another chain, remapping its asset id and re-selecting its
+    /// configured [deposit address](crate::Config::with_deposit_address) among the vault's
+    /// addresses for that asset. Fails with [`FireblocksError::UnsupportedChainId`] for chains
+    /// unknown to Fireblocks, leaving the signer untouched.
+    pub async fn switch_chain(&mut self, chain_id: u64) -> Result<(), FireblocksError> {
+        let asset_id = asset_id(chain_id)?;
+        let res = self
+            .fireblocks
+            .vault_addresses(&self.account_id, asset_id)
+            .await?;
+        self.apply_chain(chain_id, &res)
+    }
+
+    fn apply_chain(
+        &mut self,
+        chain_id: u64,
+        addresses: &[DepositAddressResponse],
+    ) -> Result<(), FireblocksError> {
+        let asset_id = asset_id(chain_id)?;
+        self.address = self.deposit_address.select(addresses)?;
+        self.asset_id = asset_id.to_owned();
+        self.chain_id = chain_id;
+        Ok(())
+    }
+
+    /// Signs a transaction like [`Signer::sign_transaction`], also returning the details of
+    /// the Fireblocks transaction which produced the signature, e.g. to keep its id for
+    /// auditing purposes.
+    pub async fn sign_transaction_with_details(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<(Signature, TransactionDetails), FireblocksError> {
+        let mut tx_with_chain = tx.clone();
+        if tx_with_chain.chain_id().is_none() {
+            // in the case we don't have a chain_id, let's use the signer chain id instead
+            tx_with_chain.set_chain_id(self.chain_id);
+        }
+        let sighash = tx_with_chain.sighash();
+        let summary = describe_transaction(&tx_with_chain, sighash);
+        self.sign(
+            tx_with_chain,
+            RawNote::Summary(summary),
+            sighash,
+            None,
+            true,
+        )
+        .await
+    }
+
+    /// Signs a message like [`Signer::sign_message`], also returning the details of the
+    /// Fireblocks transaction which produced the signature.
+    pub async fn sign_message_with_details<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<(Signature, TransactionDetails), FireblocksError> {
+        if self.typed_messages {
+            return self.sign_eth_message(message.as_ref()).await;
+        }
+        let hash = hash_message(&message);
+        let summary = format!("Sign message {:?}", hash);
+        self.sign(
+            message.as_ref(),
+            RawNote::Summary(summary),
+            hash,
+            None,
+            false,
+        )
+        .await
+    }
+
+    /// Signs a transaction like [`Signer::sign_transaction`], with `note` as the note shown to
+    /// approvers instead of the generated summary. If [`full_raw_note`](Self::full_raw_note)
+    /// is enabled, the serialized transaction is attached after the note.
+    pub async fn sign_transaction_with_note(
+        &self,
+        tx: &TypedTransaction,
+        note: &str,
+    ) -> Result<Signature, FireblocksError> {
+        let (sig, _) = self.sign_transaction_with_note_details(tx, note).await?;
+        Ok(sig)
+    }
+
+    pub(crate) async fn sign_transaction_with_note_details(
+        &self,
+        tx: &TypedTransaction,
+        note: &str,
+    ) -> Result<(Signature, TransactionDetails), FireblocksError> {
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        let sighash = tx.sighash();
+        let note = RawNote::Custom(note.to_owned());
+        self.sign(tx, note, sighash, None, true).await
+    }
+
+    /// Signs a message with the RAW operation, with `note` as the note shown to approvers
+    /// instead of the generated summary
+    pub async fn sign_message_with_note<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+        note: &str,
+    ) -> Result<Signature, FireblocksError> {
+        let hash = hash_message(&message);
+        let note = RawNote::Custom(note.to_owned());
+        let (sig, _) = self.sign(message.as_ref(), note, hash, None, false).await?;
+        Ok(sig)
+    }
+
+    /// Signs a transaction with the key at the provided BIP44 address index and change of the
+    /// vault's derivation path, for vaults which use several addresses of the same asset.
+    pub async fn sign_transaction_with_path(
+        &self,
+        tx: &TypedTransaction,
+        address_index: u32,
+        change: u32,
+    ) -> Result<Signature, FireblocksError> {
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        let sighash = tx.sighash();
+        let summary = describe_transaction(&tx, sighash);
+        let (sig, _) = self
+            .sign(
+                tx,
+                RawNote::Summary(summary),
+                sighash,
+                Some((address_index, change)),
+                true,
+            )
+            .await?;
+        Ok(sig)
+    }
+
+    /// Signs a message with the key at the provided BIP44 address index and change of the
+    /// vault's derivation path
+    pub async fn sign_message_with_path<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+        address_index: u32,
+        change: u32,
+    ) -> Result<Signature, FireblocksError> {
+        let hash = hash_message(&message);
+        let summary = format!("Sign message {:?}", hash);
+        self.sign(
+            message.as_ref(),
+            RawNote::Summary(summary),
+            hash,
+            Some((address_index, change)),
+            false,
+        )
+        .await
+        .map(|(sig, _)| sig)
+    }
+
+    /// Signs a pre-computed 32 byte digest as is, without hashing it again. The recovery id
+    /// of the signature is adjusted as per EIP-155 with the signer's chain id if `eip155` is
+    /// set, otherwise it is offset by 27.
+    pub async fn sign_hash(&self, hash: H256, eip155: bool) -> Result<Signature, FireblocksError> {
+        let summary = format!("Sign hash {:?}", hash);
+        let (sig, _) = self
+            .sign(hash, RawNote::Summary(summary), hash, None, eip155)
+            .await?;
+        Ok(sig)
+    }
+
+    /// Returns the arguments which [`sign_transaction`](Signer::sign_transaction) would
+    /// submit to Fireblocks for `tx`, without submitting them
+    pub fn preview_sign_transaction(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        let mut tx_with_chain = tx.clone();
+        if tx_with_chain.chain_id().is_none() {
+            tx_with_chain.set_chain_id(self.chain_id);
+        }
+        let sighash = tx_with_chain.sighash();
+        let summary = describe_transaction(&tx_with_chain, sighash);
+        self.raw_arguments(tx_with_chain, RawNote::Summary(summary), sighash, None)
+    }
+
+    /// Returns the arguments which [`sign_message`](Signer::sign_message) would submit to
+    /// Fireblocks for `message`, without submitting them
+    pub fn preview_sign_message<S: AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        if self.typed_messages {
+            return Ok(self.eth_message_arguments(message.as_ref()));
+        }
+        let hash = hash_message(&message);
+        let summary = format!("Sign message {:?}", hash);
+        self.raw_arguments(message.as_ref(), RawNote::Summary(summary), hash, None)
+    }
+
     async fn sign<S: serde::Serialize>(
         &self,
         preimage: S,
+        note: RawNote,
         hash: H256,
+        bip44: Option<(u32, u32)>,
         is_eip155: bool,
-    ) -> Result<Signature, FireblocksError> {
+    ) -> Result<(Signature, TransactionDetails), FireblocksError> {
+        let args = self.raw_arguments(preimage, note, hash, bip44)?;
+        let (sig, details) = self.sign_with_arguments(args, is_eip155).await?;
+        // keys at other derivation paths do not belong to the signer's address
+        if bip44.is_some() {
+            return Ok((sig, details));
+        }
+        Ok((verify_recovery(sig, hash, self.address)?, details))
+    }
+
+    fn raw_arguments<S: serde::Serialize>(
+        &self,
+        preimage: S,
+        note: RawNote,
+        hash: H256,
+        bip44: Option<(u32, u32)>,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        let note = match note {
+            RawNote::Summary(summary) if !self.full_raw_note => summary,
+            RawNote::Custom(note) if !self.full_raw_note => note,
+            note => {
+                let preimage =
+                    serde_json::to_string(&preimage).map_err(|err| FireblocksError::SerdeJson {
+                        err,
+                        text: "failed to serialize tx/message".to_owned(),
+                    })?;
+                match note {
+                    // the preimage is attached after the custom note
+                    RawNote::Custom(note) => format!("{}\n\n{}", note, preimage),
+                    RawNote::Summary(_) => preimage,
+                }
+            }
+        };
         // send the hash for signing - this will NOT take advantage
         // of the policy engine
-        let args = TransactionArguments {
-            operation: TransactionOperation::RAW,
+        let mut message = UnsignedMessage::raw(hash.as_ref());
+        if let Some((address_index, change)) = bip44 {
+            message = message.with_bip44(address_index, change);
+        }
+        Ok(self.signing_arguments(TransactionOperation::RAW, message, note))
+    }
+
+    /// Signs an EIP-191 personal message with the `TYPED_MESSAGE` operation, so that
+    /// approvers see the message itself and the policy engine applies.
+    pub async fn sign_personal_message<S: AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, FireblocksError> {
+        let (sig, _) = self.sign_eth_message(message.as_ref()).await?;
+        Ok(sig)
+    }
+
+    async fn sign_eth_message(
+        &self,
+        message: &[u8],
+    ) -> Result<(Signature, TransactionDetails), FireblocksError> {
+        let args = self.eth_message_arguments(message);
+        let (sig, details) = self.sign_with_arguments(args, false).await?;
+        Ok((
+            verify_recovery(sig, hash_message(message), self.address)?,
+            details,
+        ))
+    }
+
+    fn eth_message_arguments(&self, message: &[u8]) -> TransactionArguments {
+        let hash = hash_message(message);
+        let content = message.to_hex::<String>();
+        self.signing_arguments(
+            TransactionOperation::TYPED_MESSAGE,
+            UnsignedMessage::typed(TypedMessageType::ETH_MESSAGE, content.into()),
+            format!("Sign message {:?}", hash),
+        )
+    }
+
+    /// Signs EIP-712 typed data with the `TYPED_MESSAGE` operation. Unlike
+    /// [`sign_typed_data`](Signer::sign_typed_data), which can only sign the digest of the
+    /// payload, this sends the full typed data to Fireblocks, so that approvers see what they
+    /// are signing and the policy engine applies.
+    pub async fn sign_typed_data_message(
+        &self,
+        data: &TypedData,
+    ) -> Result<Signature, FireblocksError> {
+        let args = self.typed_data_arguments(data)?;
+        let (sig, _) = self.sign_with_arguments(args, false).await?;
+        verify_recovery(sig, typed_data_hash(data)?, self.address)
+    }
+
+    fn typed_data_arguments(
+        &self,
+        data: &TypedData,
+    ) -> Result<TransactionArguments, FireblocksError> {
+        let hash = typed_data_hash(data)?;
+        let content = serde_json::to_value(data).map_err(|err| FireblocksError::SerdeJson {
+            err,
+            text: "failed to serialize typed data".to_owned(),
+        })?;
+        let note = format!("Sign {} typed data {:?}", data.primary_type, hash);
+        Ok(self.signing_arguments(
+            TransactionOperation::TYPED_MESSAGE,
+            UnsignedMessage::typed(TypedMessageType::EIP712, content),
+            note,
+        ))
+    }
+
+    async fn sign_with_arguments(
+        &self,
+        args: TransactionArguments,
+        is_eip155: bool,
+    ) -> Result<(Signature, TransactionDetails), FireblocksError> {
+        let details = self.handle_action(args, &signing_options(), Ok).await?;
+        let sig = self.parse_signature(&details, is_eip155)?;
+        Ok((sig, details))
+    }
+
+    /// Waits for the RAW signing transaction `tx_id` to complete and returns its signature,
+    /// e.g. to pick up a signing request which was submitted before a restart. `eip155`
+    /// encodes the chain id in `v`, as for transaction signatures.
+    pub async fn resume_signature(
+        &self,
+        tx_id: &str,
+        eip155: bool,
+    ) -> Result<Signature, FireblocksError> {
+        // every transaction starts out as SUBMITTED, the actual status is polled right away
+        let details = self
+            .wait_for(
+                tx_id,
+                TransactionStatus::SUBMITTED,
+                &signing_options(),
+                self.timeouts(),
+                Ok,
+            )
+            .await?;
+        self.parse_signature(&details, eip155)
+    }
+
+    /// Parses the signature returned from the API
+    fn parse_signature(
+        &self,
+        details: &TransactionDetails,
+        is_eip155: bool,
+    ) -> Result<Signature, FireblocksError> {
+        let sig = &details
+            .signed_messages
+            .first()
+            .ok_or_else(|| FireblocksError::ParseError("no signed message".to_owned()))?
+            .signature;
+        let r = sig
+            .r
+            .parse::<U256>()
+            .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
+        let s = sig
+            .s
+            .parse::<U256>()
+            .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
+        let v = if is_eip155 {
+            to_eip155_v(sig.v as u8, self.chain_id)
+        } else {
+            sig.v + 27
+        };
+        Ok(Signature { r, s, v })
+    }
+
+    /// Signs `message` with the vault's ed25519 key for `asset_id` (e.g. `SOL`), using the
+    /// RAW operation, and returns the 64 byte signature. Unlike ECDSA digests, the message is
+    /// signed as is.
+    pub async fn sign_eddsa(
+        &self,
+        asset_id: &str,
+        message: &[u8],
+    ) -> Result<[u8; 64], FireblocksError> {
+        let note = format!("Sign {} message of {} bytes", asset_id, message.len());
+        let mut args = self.signing_arguments(
+            TransactionOperation::RAW,
+            UnsignedMessage::raw(message),
+            note,
+        );
+        args.asset_id = asset_id.to_owned();
+        if let Some(ExtraParameters::RawMessageData(ref mut data)) = args.extra_parameters {
+            data.algorithm = Some(SigningAlgorithm::MPC_EDDSA_ED25519);
+        }
+        let details = self.handle_action(args, &signing_options(), Ok).await?;
+        let full_sig = details
+            .signed_messages
+            .first()
+            .map(|msg| msg.signature.full_sig.as_str())
+            .unwrap_or_default()
+            .from_hex::<Vec<u8>>()
+            .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
+        <[u8; 64]>::try_from(full_sig).map_err(|sig| {
+            FireblocksError::ParseError(format!("expected a 64 byte signature, got {}", sig.len()))
+        })
+    }
+
+    fn signing_arguments(
+        &self,
+        operation: TransactionOperation,
+        message: UnsignedMessage,
+        note: String,
+    ) -> TransactionArguments {
+        TransactionArguments {
+            operation,
             source: TransferPeerPath {
                 peer_type: Some(PeerType::VAULT_ACCOUNT),
                 id: Some(self.account_id.clone()),
             },
             extra_parameters: Some(ExtraParameters::RawMessageData(RawMessageData {
-                messages: vec![UnsignedMessage {
-                    content: hash.as_ref().to_hex::<String>(),
-                }],
+                messages: vec![message],
+                algorithm: None,
             })),
 
             // rest is unnecessary
@@ -87,40 +633,120 @@ impl FireblocksSigner {
             destination: None,
             gas_price: None,
             gas_limit: None,
-            note: serde_json::to_string(&preimage).map_err(|err| FireblocksError::SerdeJson {
-                err,
-                text: "failed to serialize tx/message".to_owned(),
-            })?,
-        };
+            fee_level: None,
+            max_fee: None,
+            priority_fee: None,
+            customer_ref_id: None,
+            external_tx_id: None,
+            replace_tx_by_hash: None,
+            fail_on_low_fee: None,
+            force_sweep: None,
+            treat_as_gross_amount: None,
+            note: self.fireblocks.environment().tag_note(note),
+        }
+    }
+}
 
-        // Parse the signature returned from the API
-        self.handle_action(args, |details| {
-            let sig = &details.signed_messages[0].signature;
-            let r = sig
-                .r
-                .parse::<U256>()
-                .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
-            let s = sig
-                .s
-                .parse::<U256>()
-                .map_err(|err| FireblocksError::ParseError(err.to_string()))?;
-            let v = if is_eip155 {
-                to_eip155_v(sig.v as u8, self.chain_id)
-            } else {
-                sig.v + 27
-            };
-            Ok(Signature { r, s, v })
-        })
-        .await
+/// The note of a RAW signing request
+enum RawNote {
+    /// Generated by the signer, replaced by the preimage if `full_raw_note` is set
+    Summary(String),
+    /// Provided by the caller, kept in any case
+    Custom(String),
+}
+
+/// The decimals of the asset which the fees of `asset_id` transactions are paid in, e.g. the
+/// ones of ETH for ERC20 tokens
+fn fee_decimals(assets: &[SupportedAsset], asset_id: &str) -> Result<u32, FireblocksError> {
+    let find = |id: &str| {
+        assets
+            .iter()
+            .find(|asset| asset.id == id)
+            .ok_or_else(|| FireblocksError::ParseError(format!("unknown asset {}", id)))
+    };
+    let asset = find(asset_id)?;
+    let fee_asset = match asset.native_asset.as_str() {
+        "" => asset,
+        native => find(native)?,
+    };
+    fee_asset.decimals.ok_or_else(|| {
+        FireblocksError::ParseError(format!("the decimals of {} are unknown", fee_asset.id))
+    })
+}
+
+/// Makes sure that the signature recovers to `expected`, flipping the parity of `v` if the
+/// one derived from the Fireblocks response does not.
+/// The EIP-712 digest of `data`, which its signature recovers against
+fn typed_data_hash(data: &TypedData) -> Result<H256, FireblocksError> {
+    data.encode_eip712()
+        .map(H256)
+        .map_err(|err| FireblocksError::ParseError(err.to_string()))
+}
+
+fn verify_recovery(
+    sig: Signature,
+    hash: H256,
+    expected: Address,
+) -> Result<Signature, FireblocksError> {
+    if sig.verify(hash, expected).is_ok() {
+        return Ok(sig);
+    }
+    let flipped = Signature {
+        v: flip_parity(sig.v),
+        ..sig
+    };
+    match flipped.verify(hash, expected) {
+        Ok(()) => Ok(flipped),
+        Err(_) => Err(FireblocksError::SignatureMismatch {
+            expected,
+            recovered: sig.recover(hash).ok(),
+        }),
+    }
+}
+
+/// Flips the parity bit of a legacy (27/28) or EIP-155 `v`
+fn flip_parity(v: u64) -> u64 {
+    if v == 27 || v == 28 {
+        55 - v
+    } else {
+        ((v - 35) ^ 1) + 35
+    }
+}
+
+/// Summarizes a transaction for the note of its RAW signing request
+fn describe_transaction(tx: &TypedTransaction, sighash: H256) -> String {
+    let mut summary = format!("Sign transaction {:?}", sighash);
+    match tx.to() {
+        Some(NameOrAddress::Address(to)) => {
+            summary.push_str(&format!(" to {}", to_checksum(to, None)))
+        }
+        Some(NameOrAddress::Name(name)) => summary.push_str(&format!(" to {}", name)),
+        None => summary.push_str(" deploying a contract"),
+    }
+    if let Some(data) = tx.data().filter(|data| data.len() >= 4) {
+        summary.push_str(&format!(" calling 0x{}", data[..4].to_hex::<String>()));
+    }
+    summary
+}
+
+/// The options signing requests (RAW and TYPED_MESSAGE) are waited for with. Signatures are
+/// never broadcast, so they always complete at the default terminal statuses rather than the
+/// signer's, which may e.g. stop at `CONFIRMING`, a status signing requests never reach.
+fn signing_options() -> TransactionOptions {
+    TransactionOptions {
+        terminal_statuses: Some(TerminalStatuses::default()),
+        ..Default::default()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_signer;
+    use crate::{
+        mock_api::{self, MockApi},
+        test_signer, DepositAddress, MAX_NOTE_LENGTH,
+    };
     use ethers_core::types::TransactionRequest;
-    use rustc_hex::FromHex;
 
     #[tokio::test]
     async fn can_sign_transaction() {
@@ -135,6 +761,298 @@ mod tests {
         sig.verify(sighash, signer.address()).unwrap();
     }
 
+    #[test]
+    fn switches_shared_signers_to_other_chains() {
+        let signer = offline_signer();
+
+        let shared = signer.shared().with_chain_id(1u64);
+        assert!(matches!(shared.0, Cow::Borrowed(_)));
+        let switched = signer.shared().with_chain_id(5u64);
+        assert!(matches!(switched.0, Cow::Owned(_)));
+        assert_eq!(Signer::chain_id(&switched), 5);
+        assert_eq!(switched.asset_id, "ETH_TEST3");
+        assert_eq!(signer.chain_id(), 1);
+    }
+
+    fn offline_signer() -> FireblocksSigner {
+        let api = crate::FireblocksClient::new(jsonwebtoken::EncodingKey::from_secret(b""), "key");
+        FireblocksSigner::with_client(
+            api,
+            "0".to_owned(),
+            1,
+            "ETH".to_owned(),
+            Address::from_low_u64_be(1),
+        )
+    }
+
+    #[tokio::test]
+    async fn refuses_to_sign_for_unknown_chains() {
+        let signer = offline_signer().with_chain_id(424242u64);
+        assert_eq!(signer.asset_id, "");
+        assert!(matches!(
+            signer.sign_message("hello").await,
+            Err(FireblocksError::UnsupportedChainId(424242))
+        ));
+
+        let mut signer = offline_signer();
+        assert!(matches!(
+            signer.switch_chain(424242).await,
+            Err(FireblocksError::UnsupportedChainId(424242))
+        ));
+        assert_eq!((signer.chain_id, signer.asset_id.as_str()), (1, "ETH"));
+    }
+
+    #[test]
+    fn switches_to_the_configured_deposit_address() {
+        let address = |address: &str| -> DepositAddressResponse {
+            serde_json::from_value(serde_json::json!({
+                "assetId": "ETH_TEST3",
+                "address": address,
+                "type": "Permanent",
+            }))
+            .unwrap()
+        };
+        let addresses = [
+            address("0x0000000000000000000000000000000000000002"),
+            address("0x0000000000000000000000000000000000000003"),
+        ];
+
+        let mut signer = offline_signer();
+        signer.deposit_address = DepositAddress::Address(Address::from_low_u64_be(3));
+        signer.apply_chain(5, &addresses).unwrap();
+        assert_eq!(signer.address, Address::from_low_u64_be(3));
+        assert_eq!(signer.asset_id, "ETH_TEST3");
+
+        signer.deposit_address = DepositAddress::Index(0);
+        signer.apply_chain(5, &addresses).unwrap();
+        assert_eq!(signer.address, Address::from_low_u64_be(2));
+
+        signer.deposit_address = DepositAddress::Address(Address::from_low_u64_be(4));
+        assert!(matches!(
+            signer.apply_chain(1, &addresses),
+            Err(FireblocksError::DepositAddressNotFound(_))
+        ));
+        assert_eq!(signer.chain_id, 5);
+    }
+
+    #[test]
+    fn signing_ignores_the_signer_terminal_statuses() {
+        let mut signer = offline_signer();
+        let broadcast = TerminalStatuses {
+            success: vec![TransactionStatus::CONFIRMING],
+            failure: vec![TransactionStatus::FAILED],
+        };
+        signer.terminal_statuses(broadcast.clone());
+
+        // a RAW signing request completes without ever reaching CONFIRMING
+        let options = signing_options();
+        let raw = signer.terminal_statuses_for(&options);
+        assert!(raw.success.contains(&TransactionStatus::COMPLETED));
+        assert!(raw.failure.contains(&TransactionStatus::REJECTED));
+        // while submissions keep using the configured statuses
+        let options = TransactionOptions::default();
+        let submission = signer.terminal_statuses_for(&options);
+        assert_eq!(submission, &broadcast);
+    }
+
+    #[tokio::test]
+    async fn signs_typed_data_as_typed_messages() {
+        let data: TypedData = serde_json::from_value(serde_json::json!({
+            "types": {
+                "EIP712Domain": [{ "name": "name", "type": "string" }],
+                "Mail": [{ "name": "contents", "type": "string" }],
+            },
+            "primaryType": "Mail",
+            "domain": { "name": "Ether Mail" },
+            "message": { "contents": "Hello, Bob!" },
+        }))
+        .unwrap();
+        let signer = offline_signer();
+
+        let args = signer.typed_data_arguments(&data).unwrap();
+        let body = serde_json::to_value(&args).unwrap();
+        assert_eq!(body["operation"], "TYPED_MESSAGE");
+        let message = &body["extraParameters"]["rawMessageData"]["messages"][0];
+        assert_eq!(message["type"], "EIP712");
+        assert_eq!(message["content"]["primaryType"], "Mail");
+        assert_eq!(message["content"]["message"]["contents"], "Hello, Bob!");
+
+        // the digest alone is not RAW signed unless explicitly enabled
+        assert!(matches!(
+            signer.sign_typed_data(&data).await,
+            Err(FireblocksError::TypedDataRequired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn verifies_typed_message_signatures() {
+        let wallet: ethers_signers::LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        // the API reports the recovery id without the offset of 27
+        let signed = |sig: Signature| {
+            let mut details = mock_api::details("COMPLETED", "");
+            details["signedMessages"] = serde_json::json!([{
+                "content": "",
+                "algorithm": "MPC_ECDSA_SECP256K1",
+                "derivationPath": [44, 60, 0, 0, 0],
+                "signature": {
+                    "fullSig": "",
+                    "r": format!("{:x}", sig.r),
+                    "s": format!("{:x}", sig.s),
+                    "v": sig.v - 27,
+                },
+                "publicKey": "",
+            }]);
+            vec![
+                serde_json::json!({ "id": "tx", "status": "SUBMITTED" }),
+                details,
+            ]
+        };
+
+        let message = "Hello, Bob!";
+        let sig = wallet.sign_hash(hash_message(message)).unwrap();
+        let mock = MockApi::serve(signed(sig));
+        let signer = mock.signer_at(wallet.address());
+        assert_eq!(signer.sign_personal_message(message).await.unwrap(), sig);
+
+        // a signature of something else, or by another key, is refused
+        let other = wallet.sign_hash(hash_message("Hello, Alice!")).unwrap();
+        let mock = MockApi::serve(signed(other));
+        let signer = mock.signer_at(wallet.address());
+        assert!(matches!(
+            signer.sign_personal_message(message).await,
+            Err(FireblocksError::SignatureMismatch { .. })
+        ));
+
+        let data: TypedData = serde_json::from_value(serde_json::json!({
+            "types": {
+                "EIP712Domain": [{ "name": "name", "type": "string" }],
+                "Mail": [{ "name": "contents", "type": "string" }],
+            },
+            "primaryType": "Mail",
+            "domain": { "name": "Ether Mail" },
+            "message": { "contents": "Hello, Bob!" },
+        }))
+        .unwrap();
+        let sig = wallet.sign_hash(typed_data_hash(&data).unwrap()).unwrap();
+        let mock = MockApi::serve(signed(sig));
+        let signer = mock.signer_at(wallet.address());
+        assert_eq!(signer.sign_typed_data_message(&data).await.unwrap(), sig);
+
+        let mock = MockApi::serve(signed(other));
+        let signer = mock.signer_at(wallet.address());
+        assert!(matches!(
+            signer.sign_typed_data_message(&data).await,
+            Err(FireblocksError::SignatureMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn normalizes_fees_to_wei() {
+        let info = FeeInfo {
+            network_fee: Some("0.00042".to_owned()),
+            service_fee: None,
+            gas_price: Some("20".to_owned()),
+        };
+        let fees = FeeBreakdown::from_fee_info(&info, 18).unwrap();
+        assert_eq!(fees.network_fee, U256::from(420_000_000_000_000u64));
+        assert_eq!(fees.gas_price, Some(U256::from(20_000_000_000u64)));
+        assert_eq!(fees.gas_used, None);
+        assert_eq!(fees.service_fee, U256::zero());
+
+        // a native asset with fewer decimals
+        let fees = FeeBreakdown::from_fee_info(&info, 8).unwrap();
+        assert_eq!(fees.network_fee, U256::from(42_000));
+    }
+
+    #[test]
+    fn uses_the_decimals_of_the_fee_asset() {
+        let asset = |id: &str, native: &str, decimals: Option<u32>| SupportedAsset {
+            id: id.to_owned(),
+            name: id.to_owned(),
+            asset_type: "BASE_ASSET".to_owned(),
+            contract_address: String::new(),
+            native_asset: native.to_owned(),
+            decimals,
+        };
+        let assets = [
+            asset("ETH", "", Some(18)),
+            asset("USDC", "ETH", Some(6)),
+            asset("CELO", "CELO", Some(18)),
+            asset("XYZ", "", None),
+        ];
+        assert_eq!(fee_decimals(&assets, "ETH").unwrap(), 18);
+        // token transfers pay their fees in the native asset
+        assert_eq!(fee_decimals(&assets, "USDC").unwrap(), 18);
+        assert_eq!(fee_decimals(&assets, "CELO").unwrap(), 18);
+        assert!(fee_decimals(&assets, "XYZ").is_err());
+        assert!(fee_decimals(&assets, "BTC").is_err());
+    }
+
+    #[test]
+    fn bounds_note_length() {
+        let mut signer = offline_signer();
+        assert!(signer.check_note(&"a".repeat(MAX_NOTE_LENGTH)).is_ok());
+        assert!(matches!(
+            signer.check_note(&"a".repeat(MAX_NOTE_LENGTH + 1)),
+            Err(FireblocksError::NoteTooLong { length, max })
+                if length == MAX_NOTE_LENGTH + 1 && max == MAX_NOTE_LENGTH
+        ));
+        // characters are counted, not bytes
+        assert!(signer.check_note(&"é".repeat(MAX_NOTE_LENGTH)).is_ok());
+
+        signer.max_note_length(10);
+        assert!(signer.check_note("0123456789").is_ok());
+        assert!(signer.check_note("0123456789a").is_err());
+    }
+
+    #[test]
+    fn summarizes_raw_notes() {
+        let to: Address = "0xcBE74E21B070A979b9d6426b11e876d4cB618DaF"
+            .parse()
+            .unwrap();
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(to)
+            .data(vec![0xea, 0xd7, 0x10, 0xc4, 0, 0, 0, 0])
+            .into();
+        assert_eq!(
+            describe_transaction(&tx, H256::zero()),
+            format!(
+                "Sign transaction {:?} to 0xcBE74E21B070A979b9d6426b11e876d4cB618DaF calling 0xead710c4",
+                H256::zero()
+            )
+        );
+
+        let deployment: TypedTransaction = TransactionRequest::new().into();
+        assert!(describe_transaction(&deployment, H256::zero()).ends_with("deploying a contract"));
+    }
+
+    #[test]
+    fn fixes_signature_parity() {
+        let wallet: ethers_signers::LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let hash = H256::repeat_byte(0x42);
+        let sig = wallet.sign_hash(hash).unwrap();
+
+        let flipped = Signature {
+            v: flip_parity(sig.v),
+            ..sig
+        };
+        assert_eq!(
+            verify_recovery(flipped, hash, wallet.address()).unwrap(),
+            sig
+        );
+        assert_eq!(flip_parity(flip_parity(5 * 2 + 35)), 5 * 2 + 35);
+        assert!(matches!(
+            verify_recovery(sig, hash, Address::zero()),
+            Err(FireblocksError::SignatureMismatch { .. })
+        ));
+    }
+
     #[tokio::test]
     async fn can_sign_msg() {
         let signer = test_signer().await;
@@ -0,0 +1,60 @@
+//! Thin wrappers around the [`metrics`](https://docs.rs/metrics) facade. When the `metrics`
+//! feature is disabled, every function here compiles down to a no-op.
+//!
+//! All metrics are labeled with the `environment` the client is pointed at.
+use crate::Environment;
+use std::time::Duration;
+
+/// Recorded whenever a request was rejected with the primary credentials and had to be
+/// retried with the secondary ones, i.e. during an API key rotation.
+#[allow(unused_variables)]
+pub(crate) fn secondary_key_used(env: Environment) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("fireblocks_secondary_key_used_total", "environment" => env.as_str())
+        .increment(1);
+}
+
+/// Recorded for every API request
+#[allow(unused_variables)]
+pub(crate) fn api_request(env: Environment) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("fireblocks_api_requests_total", "environment" => env.as_str()).increment(1);
+}
+
+/// Recorded whenever the API responds with a non-2xx status
+#[allow(unused_variables)]
+pub(crate) fn api_error(env: Environment, status: u16) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(
+        "fireblocks_api_errors_total",
+        "environment" => env.as_str(),
+        "status" => status.to_string()
+    )
+    .increment(1);
+}
+
+/// Recorded whenever the API responds with 429 Too Many Requests
+#[allow(unused_variables)]
+pub(crate) fn rate_limited(env: Environment) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("fireblocks_rate_limited_total", "environment" => env.as_str()).increment(1);
+}
+
+/// Recorded once a transaction got approved and signed, with the time since its submission
+#[allow(unused_variables)]
+#[cfg_attr(not(feature = "ethers"), allow(dead_code))]
+pub(crate) fn approval_latency(env: Environment, latency: Duration) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!("fireblocks_approval_latency_seconds", "environment" => env.as_str())
+        .record(latency.as_secs_f64());
+}
+
+/// Recorded whenever a signer starts (`delta = 1`) or stops (`delta = -1`) waiting on a
+/// transaction
+#[allow(unused_variables)]
+#[cfg_attr(not(feature = "ethers"), allow(dead_code))]
+pub(crate) fn in_flight(env: Environment, delta: f64) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!("fireblocks_in_flight_transactions", "environment" => env.as_str())
+        .increment(delta);
+}
@@ -1,31 +1,51 @@
+use crate::FireblocksError;
+use rustc_hex::ToHex;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VaultAccountPaginatedResponse {
-    accounts: Vec<VaultAccountResponse>,
-    paging: Paging,
-    previous_url: Option<String>,
-    next_url: Option<String>,
+    pub accounts: Vec<VaultAccountResponse>,
+    pub paging: Paging,
+    pub previous_url: Option<String>,
+    pub next_url: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VaultAccountResponse {
-    id: String,
-    name: String,
+    pub id: String,
+    pub name: String,
     #[serde(rename = "hiddenOnUI")]
-    hidden_on_ui: bool,
-    assets: Vec<AssetResponse>,
-    customer_ref_id: Option<String>,
-    auto_fuel: bool,
+    pub hidden_on_ui: bool,
+    pub assets: Vec<AssetResponse>,
+    pub customer_ref_id: Option<String>,
+    pub auto_fuel: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Paging {
-    before: Option<String>,
-    after: Option<String>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameVaultRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCustomerRefIdRequest {
+    pub customer_ref_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAutoFuelRequest {
+    pub auto_fuel: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,21 +69,188 @@ pub struct CreateVaultResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct CreateVaultAssetResponse {
+    pub id: String,
+    pub address: String,
+    pub legacy_address: Option<String>,
+    pub tag: Option<String>,
+    pub eos_account_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AssetResponse {
-    id: String,
-    total: String,
+    pub id: String,
+    pub total: String,
     /// DEPRECATED
-    balance: Option<String>,
+    pub balance: Option<String>,
     #[serde(rename = "lockedAmount")]
-    locked_amount: Option<String>,
-    available: Option<String>,
-    pending: Option<String>,
-    self_staked_cpu: Option<String>,
-    self_staked_network: Option<String>,
-    pending_refund_cpu: Option<String>,
-    pending_refund_network: Option<String>,
-    total_staked_cpu: Option<String>,
-    total_staked_network: Option<String>,
+    pub locked_amount: Option<String>,
+    pub available: Option<String>,
+    pub pending: Option<String>,
+    #[serde(rename = "selfStakedCPU")]
+    pub self_staked_cpu: Option<String>,
+    pub self_staked_network: Option<String>,
+    #[serde(rename = "pendingRefundCPU")]
+    pub pending_refund_cpu: Option<String>,
+    pub pending_refund_network: Option<String>,
+    #[serde(rename = "totalStakedCPU")]
+    pub total_staked_cpu: Option<String>,
+    pub total_staked_network: Option<String>,
+}
+
+impl AssetResponse {
+    /// Parses the balances of the asset. Missing balances are treated as zero.
+    pub fn balances(&self) -> Result<Balances, FireblocksError> {
+        Ok(Balances {
+            total: self.total.parse()?,
+            available: parse_amount(&self.available)?,
+            pending: parse_amount(&self.pending)?,
+            locked: parse_amount(&self.locked_amount)?,
+        })
+    }
+
+    /// Parses the staking balances of the asset. Missing balances are treated as zero.
+    pub fn staking_balances(&self) -> Result<StakingBalances, FireblocksError> {
+        Ok(StakingBalances {
+            self_staked_cpu: parse_amount(&self.self_staked_cpu)?,
+            self_staked_network: parse_amount(&self.self_staked_network)?,
+            pending_refund_cpu: parse_amount(&self.pending_refund_cpu)?,
+            pending_refund_network: parse_amount(&self.pending_refund_network)?,
+            total_staked_cpu: parse_amount(&self.total_staked_cpu)?,
+            total_staked_network: parse_amount(&self.total_staked_network)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The balances of an asset, aggregated across all the vault accounts of the workspace
+pub struct VaultAssetSummary {
+    pub asset_id: String,
+    pub total: String,
+    pub available: Option<String>,
+    pub pending: Option<String>,
+    pub frozen: Option<String>,
+    pub locked_amount: Option<String>,
+}
+
+impl VaultAssetSummary {
+    /// Parses the aggregate balances of the asset. Missing balances are treated as zero.
+    pub fn balances(&self) -> Result<Balances, FireblocksError> {
+        Ok(Balances {
+            total: self.total.parse()?,
+            available: parse_amount(&self.available)?,
+            pending: parse_amount(&self.pending)?,
+            locked: parse_amount(&self.locked_amount)?,
+        })
+    }
+}
+
+fn parse_amount(amount: &Option<String>) -> Result<Amount, FireblocksError> {
+    match amount.as_deref() {
+        None | Some("") => Ok(Amount::default()),
+        Some(amount) => amount.parse(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+/// An exact, non-negative decimal amount as reported by the API (e.g. `"1.000000000000000001"`),
+/// denominated in the asset's units. Unlike a float, it keeps every digit of 18 decimal assets.
+pub struct Amount {
+    // the amount is `units / 10^scale`, with no trailing zeros in the fractional part
+    units: u128,
+    scale: u32,
+}
+
+impl Amount {
+    /// Converts the amount to the asset's base units (e.g. wei for an 18 decimals asset).
+    /// Returns `None` if the amount has more fractional digits than `decimals`, or overflows.
+    pub fn to_base_units(&self, decimals: u32) -> Option<u128> {
+        let shift = decimals.checked_sub(self.scale)?;
+        self.units.checked_mul(10u128.checked_pow(shift)?)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.units == 0
+    }
+}
+
+impl std::str::FromStr for Amount {
+    type Err = FireblocksError;
+
+    /// Parses a decimal amount, optionally in scientific notation (e.g. `1e-7`)
+    fn from_str(amount: &str) -> Result<Self, Self::Err> {
+        let err = || FireblocksError::ParseError(format!("invalid amount {:?}", amount));
+        let (mantissa, exponent) = match amount.trim().split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().map_err(|_| err())?),
+            None => (amount.trim(), 0),
+        };
+        let mantissa = mantissa.strip_prefix('+').unwrap_or(mantissa);
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int.is_empty() && frac.is_empty() {
+            return Err(err());
+        }
+        let mut units: u128 = 0;
+        for c in int.chars().chain(frac.chars()) {
+            let digit = c.to_digit(10).ok_or_else(err)?;
+            units = units
+                .checked_mul(10)
+                .and_then(|units| units.checked_add(digit.into()))
+                .ok_or_else(err)?;
+        }
+        let mut scale = frac.len() as i64 - exponent;
+        if scale < 0 {
+            let shift = u32::try_from(-scale).map_err(|_| err())?;
+            units = 10u128
+                .checked_pow(shift)
+                .and_then(|factor| units.checked_mul(factor))
+                .ok_or_else(err)?;
+            scale = 0;
+        }
+        while scale > 0 && units.is_multiple_of(10) {
+            units /= 10;
+            scale -= 1;
+        }
+        let scale = u32::try_from(scale).map_err(|_| err())?;
+        Ok(Self { units, scale })
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.units.to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+        if digits.len() <= scale {
+            write!(f, "0.{}{}", "0".repeat(scale - digits.len()), digits)
+        } else {
+            let (int, frac) = digits.split_at(digits.len() - scale);
+            write!(f, "{}.{}", int, frac)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The staking balances of a vault asset (e.g. EOS CPU/NET), denominated in the asset's units
+pub struct StakingBalances {
+    pub self_staked_cpu: Amount,
+    pub self_staked_network: Amount,
+    pub pending_refund_cpu: Amount,
+    pub pending_refund_network: Amount,
+    pub total_staked_cpu: Amount,
+    pub total_staked_network: Amount,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The balances of a vault asset, denominated in the asset's units (e.g. ETH, not wei)
+pub struct Balances {
+    pub total: Amount,
+    pub available: Amount,
+    pub pending: Amount,
+    pub locked: Amount,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -85,6 +272,35 @@ pub struct DepositAddressResponse {
     pub address_format: Option<String>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAddressRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_ref_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateAddressRequest {
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A newly created deposit address of a vault wallet
+pub struct CreateAddressResponse {
+    pub address: String,
+    #[serde(default)]
+    pub legacy_address: Option<String>,
+    #[serde(default)]
+    pub enterprise_address: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub bip44_address_index: Option<u32>,
+}
+
 // The APIs feel a bit weird: In trying to create a unified API, it might be good
 // to combine these options in enums
 #[derive(Debug, Serialize, Deserialize)]
@@ -102,14 +318,49 @@ pub struct TransactionArguments {
     pub gas_price: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gas_limit: Option<String>,
+    /// Lets Fireblocks price the transaction, instead of explicit gas prices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_level: Option<FeeLevel>,
+    /// The EIP-1559 max fee per gas
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee: Option<String>,
+    /// The EIP-1559 max priority fee per gas
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority_fee: Option<String>,
     pub note: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_ref_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_tx_id: Option<String>,
+    /// The hash of a stuck transaction which this one replaces, i.e. re-uses the nonce of
+    #[serde(rename = "replaceTxByHash", skip_serializing_if = "Option::is_none")]
+    pub replace_tx_by_hash: Option<String>,
+    /// Fail the transaction instead of broadcasting it if the fee is lower than the network's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_on_low_fee: Option<bool>,
+    /// Send the whole balance, for assets whose addresses must be emptied, e.g. DOT
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_sweep: Option<bool>,
+    /// Deduct the fee from the amount, so that the source is debited exactly the amount
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub treat_as_gross_amount: Option<bool>,
 }
 
+/// The `extraParameters` of a transaction. Fireblocks keeps adding new modes, so anything
+/// which is not modeled here can still be sent (and received) through `Other`.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ExtraParameters {
+    /// Hex-encoded calldata of a `CONTRACT_CALL`
     ContractCallData(String),
+    /// The messages to be signed by a `RAW` operation
     RawMessageData(RawMessageData),
+    /// Hex-encoded program instructions (e.g. for Solana program calls)
+    ProgramCallData(String),
+    /// Escape hatch for extra parameters which are not supported by this crate yet. The
+    /// value is sent as-is, so it must be the full `extraParameters` object.
+    #[serde(untagged)]
+    Other(serde_json::Value),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -139,6 +390,15 @@ pub struct OneTimeAddress {
     pub tag: Option<String>,
 }
 
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The fee levels Fireblocks prices transactions at, based on the network's conditions
+pub enum FeeLevel {
+    LOW,
+    MEDIUM,
+    HIGH,
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(clippy::upper_case_acronyms)]
@@ -146,6 +406,7 @@ pub enum TransactionOperation {
     TRANSFER,
     RAW,
     CONTRACT_CALL,
+    TYPED_MESSAGE,
 
     MINT,
     BURN,
@@ -154,21 +415,145 @@ pub enum TransactionOperation {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum PeerType {
     VAULT_ACCOUNT,
     EXCHANGE_ACCOUNT,
     INTERNAL_WALLET,
     EXTERNAL_WALLET,
+    CONTRACT,
     ONE_TIME_ADDRESS,
     NETWORK_CONNECTION,
     FIAT_ACCOUNT,
     COMPOUND,
+    /// Any peer type this crate does not know about (e.g. `UNKNOWN`, the source of an
+    /// incoming transfer from an unknown address), kept as sent so that it round-trips
+    Other(String),
+}
+
+impl PeerType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PeerType::VAULT_ACCOUNT => "VAULT_ACCOUNT",
+            PeerType::EXCHANGE_ACCOUNT => "EXCHANGE_ACCOUNT",
+            PeerType::INTERNAL_WALLET => "INTERNAL_WALLET",
+            PeerType::EXTERNAL_WALLET => "EXTERNAL_WALLET",
+            PeerType::CONTRACT => "CONTRACT",
+            PeerType::ONE_TIME_ADDRESS => "ONE_TIME_ADDRESS",
+            PeerType::NETWORK_CONNECTION => "NETWORK_CONNECTION",
+            PeerType::FIAT_ACCOUNT => "FIAT_ACCOUNT",
+            PeerType::COMPOUND => "COMPOUND",
+            PeerType::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for PeerType {
+    fn from(peer_type: String) -> Self {
+        match peer_type.as_str() {
+            "VAULT_ACCOUNT" => PeerType::VAULT_ACCOUNT,
+            "EXCHANGE_ACCOUNT" => PeerType::EXCHANGE_ACCOUNT,
+            "INTERNAL_WALLET" => PeerType::INTERNAL_WALLET,
+            "EXTERNAL_WALLET" => PeerType::EXTERNAL_WALLET,
+            "CONTRACT" => PeerType::CONTRACT,
+            "ONE_TIME_ADDRESS" => PeerType::ONE_TIME_ADDRESS,
+            "NETWORK_CONNECTION" => PeerType::NETWORK_CONNECTION,
+            "FIAT_ACCOUNT" => PeerType::FIAT_ACCOUNT,
+            "COMPOUND" => PeerType::COMPOUND,
+            _ => PeerType::Other(peer_type),
+        }
+    }
+}
+
+impl From<PeerType> for String {
+    fn from(peer_type: PeerType) -> Self {
+        match peer_type {
+            PeerType::Other(other) => other,
+            known => known.as_str().to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The `{ "success": true }` body returned by management endpoints
+pub struct OperationSuccessResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The fee of the self-send which replaces a dropped transaction. Fireblocks picks one if
+/// neither is set.
+pub struct DropTransactionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_level: Option<FeeLevel>,
+    /// In Gwei
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The response to freezing or unfreezing a transaction
+pub struct FreezeTransactionResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropTransactionResponse {
+    pub success: bool,
+    /// The ids of the transactions created to replace the dropped one
+    #[serde(default)]
+    pub transactions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The body returned by the API alongside a non-2xx status
+pub struct ErrorResponse {
+    pub message: String,
+    pub code: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// An internal or external (whitelisted) wallet
+pub struct WalletResponse {
+    pub id: String,
+    pub name: String,
+    pub customer_ref_id: Option<String>,
+    pub assets: Vec<WalletAssetResponse>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct WalletAssetResponse {
+    pub id: String,
+    pub status: WalletAssetStatus,
+    pub address: Option<String>,
+    pub tag: Option<String>,
+    pub balance: Option<String>,
+    pub locked_amount: Option<String>,
+    pub activation_time: Option<String>,
+}
+
+#[allow(non_camel_case_types)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The whitelisting status of a wallet asset. Only `APPROVED` entries can be used as
+/// transaction destinations.
+pub enum WalletAssetStatus {
+    WAITING_FOR_APPROVAL,
+    APPROVED,
+    CANCELLED,
+    REJECTED,
+    FAILED,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateTransactionResponse {
     pub id: String,
     pub status: TransactionStatus,
@@ -176,7 +561,7 @@ pub struct CreateTransactionResponse {
 
 #[allow(non_camel_case_types)]
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TransactionStatus {
     SUBMITTED,
     QUEUED,
@@ -205,7 +590,63 @@ pub enum TransactionStatus {
     BLOCKED,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl TransactionStatus {
+    /// Whether the transaction is still waiting to be approved and signed
+    pub fn is_pending_approval(&self) -> bool {
+        use TransactionStatus::*;
+        matches!(
+            self,
+            SUBMITTED
+                | QUEUED
+                | PENDING_AUTHORIZATION
+                | PENDING_SIGNATURE
+                | PENDING_3RD_PARTY_MANUAL_APPROVAL
+                | PENDING_3RD_PARTY
+                | PENDING
+        )
+    }
+
+    /// Whether the transaction reached a final status and will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        use TransactionStatus::*;
+        matches!(
+            self,
+            COMPLETED | CANCELLED | REJECTED | FAILED | TIMEOUT | BLOCKED
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// Query filters for listing transactions. Unset filters are not applied.
+pub struct TransactionFilter {
+    /// Only transactions created before this time (milliseconds since Epoch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<u64>,
+    /// Only transactions created after this time (milliseconds since Epoch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TransactionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<PeerType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
+    #[serde(rename = "destType", skip_serializing_if = "Option::is_none")]
+    pub dest_type: Option<PeerType>,
+    #[serde(rename = "destId", skip_serializing_if = "Option::is_none")]
+    pub dest_id: Option<String>,
+    /// Comma-separated list of asset ids
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    /// Maximum number of transactions per page (at most 500)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransactionDetails {
     pub id: String,
@@ -216,9 +657,291 @@ pub struct TransactionDetails {
     pub sub_status: String,
 
     pub signed_messages: Vec<SignedMessageResponse>,
+
+    #[serde(default)]
+    pub fee_info: Option<FeeInfo>,
+
+    /// Milliseconds since Epoch at which the transaction was created
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    #[serde(default)]
+    pub source: Option<TransferPeerPathResponse>,
+    #[serde(default)]
+    pub destination: Option<TransferPeerPathResponse>,
+    #[serde(default)]
+    pub source_address: Option<String>,
+    #[serde(default)]
+    pub destination_address: Option<String>,
+    #[serde(default)]
+    pub amount_info: Option<AmountInfo>,
+    #[serde(default)]
+    pub num_of_confirmations: Option<u64>,
+    #[serde(default)]
+    pub aml_screening_result: Option<AmlScreeningResult>,
+    /// The id the transaction was submitted with, e.g. an internal order id
+    #[serde(default)]
+    pub external_tx_id: Option<String>,
+    /// The ids of the users who signed the transaction
+    #[serde(default)]
+    pub signed_by: Vec<String>,
+    /// The id of the user who rejected the transaction
+    #[serde(default)]
+    pub rejected_by: Option<String>,
+    /// The approval groups of the transaction's authorization policy, and their votes
+    #[serde(default)]
+    pub authorization_info: Option<AuthorizationInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl TransactionDetails {
+    /// How many of the approvals required by the transaction's authorization policy were
+    /// collected, if it has one
+    pub fn approval_progress(&self) -> Option<ApprovalProgress> {
+        let info = self.authorization_info.as_ref()?;
+        let progress = info.groups.iter().map(|group| {
+            let approved = group
+                .users
+                .values()
+                .filter(|status| status.as_str() == "APPROVED")
+                .count() as u32;
+            ApprovalProgress {
+                collected: approved.min(group.th),
+                required: group.th,
+            }
+        });
+        if info.logic.as_deref() == Some("OR") {
+            // any group can approve, so the one closest to its threshold is reported
+            progress.min_by_key(|progress| progress.required - progress.collected)
+        } else {
+            Some(progress.fold(ApprovalProgress::default(), |total, group| {
+                ApprovalProgress {
+                    collected: total.collected + group.collected,
+                    required: total.required + group.required,
+                }
+            }))
+        }
+    }
+
+    /// Whether the transaction was stopped by AML screening, based on its sub status
+    pub fn is_aml_rejected(&self) -> bool {
+        let sub_status = self.sub_status.to_uppercase();
+        self.status.is_terminal()
+            && self.status != TransactionStatus::COMPLETED
+            && (sub_status.contains("AML") || sub_status.contains("SCREENING"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The approval groups of a transaction's authorization policy
+pub struct AuthorizationInfo {
+    #[serde(default)]
+    pub allow_operator_as_authorizer: Option<bool>,
+    /// Whether all groups (`AND`) or any group (`OR`) must approve
+    #[serde(default)]
+    pub logic: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<AuthorizationGroup>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A group of approvers, of which `th` must approve
+pub struct AuthorizationGroup {
+    pub th: u32,
+    /// The vote of each user of the group, e.g. `PENDING_AUTHORIZATION` or `APPROVED`
+    #[serde(default)]
+    pub users: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The number of approvals a transaction collected, out of the number it requires
+pub struct ApprovalProgress {
+    pub collected: u32,
+    pub required: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The verdict of the AML screening provider for a transaction
+pub struct AmlScreeningResult {
+    pub provider: Option<String>,
+    /// The provider's raw response
+    pub payload: Option<serde_json::Value>,
+    pub screening_status: Option<String>,
+    pub bypass_reason: Option<String>,
+    /// Milliseconds since Epoch at which the transaction was screened
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The source or destination of a transaction, as reported by Fireblocks
+pub struct TransferPeerPathResponse {
+    #[serde(rename = "type")]
+    pub peer_type: PeerType,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The amounts of a transaction, denominated in the asset's units
+pub struct AmountInfo {
+    pub amount: Option<String>,
+    pub requested_amount: Option<String>,
+    pub net_amount: Option<String>,
+    #[serde(rename = "amountUSD")]
+    pub amount_usd: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The fees of a transaction. Fees are denominated in the fee asset's units (e.g. ETH),
+/// the gas price in gwei.
+pub struct FeeInfo {
+    pub network_fee: Option<String>,
+    pub service_fee: Option<String>,
+    pub gas_price: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// An asset supported by the workspace
+pub struct SupportedAsset {
+    pub id: String,
+    pub name: String,
+    /// E.g. `BASE_ASSET` or `ERC20`
+    #[serde(rename = "type")]
+    pub asset_type: String,
+    /// Empty for base assets
+    #[serde(default)]
+    pub contract_address: String,
+    /// The id of the asset fees are paid in, e.g. `ETH` for ERC20 tokens
+    #[serde(default)]
+    pub native_asset: String,
+    #[serde(default)]
+    pub decimals: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// An exchange account connected to the workspace
+pub struct ExchangeAccount {
+    pub id: String,
+    /// The exchange, e.g. `BINANCE` or `COINBASEPRO`
+    #[serde(rename = "type")]
+    pub exchange_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<ExchangeAsset>,
+    #[serde(default)]
+    pub is_subaccount: bool,
+    #[serde(default)]
+    pub main_account_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The balance of an asset in an exchange account, denominated in the asset's units
+pub struct ExchangeAsset {
+    pub id: String,
+    pub total: String,
+    #[serde(default)]
+    pub available: Option<String>,
+    #[serde(default)]
+    pub locked_amount: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A transfer between the main account of an exchange and one of its sub / trading accounts
+pub struct ExchangeInternalTransferRequest {
+    pub asset: String,
+    pub amount: String,
+    /// The account type funds are moved from, e.g. `FUNDING` or `EXCHANGE`
+    pub source_type: String,
+    /// The account type funds are moved to
+    pub dest_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A conversion between two assets of an exchange account, e.g. USD to USDC
+pub struct ExchangeConvertRequest {
+    pub src_asset: String,
+    pub dest_asset: String,
+    pub amount: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The balances and auto-fueling configuration of the gas station
+pub struct GasStationInfo {
+    /// The balance of the gas station per asset id
+    #[serde(default)]
+    pub balance: std::collections::HashMap<String, String>,
+    pub configuration: GasStationConfiguration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The thresholds of auto-fueling, denominated in the native asset's units (e.g. ETH)
+pub struct GasStationConfiguration {
+    /// Vault accounts whose balance drops below this threshold are fueled
+    pub gas_threshold: String,
+    /// The balance vault accounts are fueled up to
+    pub gas_cap: String,
+    /// No fueling happens while the gas price (in gwei) is above this, if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gas_price: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The result of validating an address for an asset
+pub struct AddressValidation {
+    /// Whether the address is well-formed for the asset
+    pub is_valid: bool,
+    /// Whether the address is active on chain (only reported by some assets)
+    #[serde(default)]
+    pub is_active: bool,
+    /// Whether transfers to the address need a tag / memo
+    #[serde(default)]
+    pub requires_tag: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The network fee Fireblocks estimates for a transfer of an asset, at every fee level
+pub struct NetworkFeeEstimate {
+    pub low: NetworkFee,
+    pub medium: NetworkFee,
+    pub high: NetworkFee,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The estimated fee of a single fee level. Only the fields which apply to the asset are
+/// set, e.g. `base_fee` and `priority_fee` for EIP-1559 assets. Fees are denominated in the
+/// fee asset's units, gas prices in gwei.
+pub struct NetworkFee {
+    #[serde(default)]
+    pub network_fee: Option<String>,
+    #[serde(default)]
+    pub gas_price: Option<String>,
+    #[serde(default)]
+    pub gas_limit: Option<String>,
+    #[serde(default)]
+    pub base_fee: Option<String>,
+    #[serde(default)]
+    pub priority_fee: Option<String>,
+    /// For UTXO assets
+    #[serde(default)]
+    pub fee_per_byte: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SignedMessageResponse {
     content: String,
@@ -228,12 +951,18 @@ pub struct SignedMessageResponse {
     public_key: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SignatureResponse {
     pub full_sig: String,
+    /// Only set for ECDSA signatures
+    #[serde(default)]
     pub r: String,
+    /// Only set for ECDSA signatures
+    #[serde(default)]
     pub s: String,
+    /// Only set for ECDSA signatures
+    #[serde(default)]
     pub v: u64,
 }
 
@@ -241,10 +970,489 @@ pub struct SignatureResponse {
 #[serde(rename_all = "camelCase")]
 pub struct RawMessageData {
     pub messages: Vec<UnsignedMessage>,
+    /// The algorithm to sign with, defaults to the asset's algorithm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<SigningAlgorithm>,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The signing algorithms supported by the `RAW` operation
+pub enum SigningAlgorithm {
+    /// ECDSA over secp256k1, used by EVM chains and Bitcoin
+    MPC_ECDSA_SECP256K1,
+    /// EdDSA over ed25519, used by Solana, Aptos and the like
+    MPC_EDDSA_ED25519,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UnsignedMessage {
-    pub content: String,
+    /// The hex-encoded digest for `RAW` operations, the message for `TYPED_MESSAGE`
+    /// operations (a hex string for `ETH_MESSAGE`, the typed data object for `EIP712`)
+    pub content: serde_json::Value,
+    /// The type of the message, only set for `TYPED_MESSAGE` operations
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<TypedMessageType>,
+    /// The address index of the key to sign with, defaults to the vault's first address
+    #[serde(rename = "bip44addressIndex", skip_serializing_if = "Option::is_none")]
+    pub bip44_address_index: Option<u32>,
+    /// The change part of the key's derivation path
+    #[serde(rename = "bip44change", skip_serializing_if = "Option::is_none")]
+    pub bip44_change: Option<u32>,
+}
+
+impl UnsignedMessage {
+    /// A digest to be signed by a `RAW` operation
+    pub fn raw(digest: &[u8]) -> Self {
+        Self {
+            content: digest.to_hex::<String>().into(),
+            message_type: None,
+            bip44_address_index: None,
+            bip44_change: None,
+        }
+    }
+
+    /// A message to be signed by a `TYPED_MESSAGE` operation
+    pub fn typed(message_type: TypedMessageType, content: serde_json::Value) -> Self {
+        Self {
+            content,
+            message_type: Some(message_type),
+            bip44_address_index: None,
+            bip44_change: None,
+        }
+    }
+
+    /// Signs the message with the key at `m/44'/coin'/account'/change/address_index`
+    pub fn with_bip44(mut self, address_index: u32, change: u32) -> Self {
+        self.bip44_address_index = Some(address_index);
+        self.bip44_change = Some(change);
+        self
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The kinds of messages which can be signed by a `TYPED_MESSAGE` operation
+pub enum TypedMessageType {
+    /// An EIP-191 personal message
+    ETH_MESSAGE,
+    /// EIP-712 typed structured data
+    EIP712,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_vault_balances() {
+        let vault: VaultAccountResponse = serde_json::from_value(json!({
+            "id": "1",
+            "name": "treasury",
+            "hiddenOnUI": false,
+            "autoFuel": false,
+            "assets": [{
+                "id": "ETH_TEST3",
+                "total": "1.5",
+                "available": "1.25",
+                "pending": "0",
+                "lockedAmount": "0.25",
+                "selfStakedCPU": "2.5",
+                "totalStakedNetwork": "1"
+            }]
+        }))
+        .unwrap();
+        let balances = vault.assets[0].balances().unwrap();
+        let amount = |amount: &str| amount.parse::<Amount>().unwrap();
+        assert_eq!(
+            balances,
+            Balances {
+                total: amount("1.5"),
+                available: amount("1.25"),
+                pending: Amount::default(),
+                locked: amount("0.25")
+            }
+        );
+        let staking = vault.assets[0].staking_balances().unwrap();
+        assert_eq!(staking.self_staked_cpu, amount("2.5"));
+        assert_eq!(staking.total_staked_network, amount("1"));
+        assert!(staking.pending_refund_cpu.is_zero());
+    }
+
+    #[test]
+    fn parses_amounts_exactly() {
+        let amount = |amount: &str| amount.parse::<Amount>().unwrap();
+        // a float would round this to 1
+        let wei = amount("1.000000000000000001");
+        assert_eq!(wei.to_base_units(18), Some(1_000_000_000_000_000_001));
+        assert_eq!(wei.to_string(), "1.000000000000000001");
+        assert_eq!(wei.to_base_units(6), None);
+
+        assert_eq!(amount("0.10"), amount("0.1"));
+        assert_eq!(amount("1e-7").to_string(), "0.0000001");
+        assert_eq!(amount("2.5E3").to_base_units(0), Some(2500));
+        assert_eq!(amount("0").to_string(), "0");
+        assert!(amount("0.000").is_zero());
+        for invalid in ["", ".", "-1", "1.2.3", "abc", "1e"] {
+            assert!(invalid.parse::<Amount>().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn detects_aml_rejections() {
+        let details: TransactionDetails = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "assetId": "ETH",
+            "txHash": "",
+            "status": "REJECTED",
+            "subStatus": "REJECTED_AML_SCREENING",
+            "signedMessages": [],
+            "amlScreeningResult": { "provider": "ELLIPTIC", "screeningStatus": "REJECTED" }
+        }))
+        .unwrap();
+        assert!(details.is_aml_rejected());
+        assert_eq!(
+            details.aml_screening_result.unwrap().provider.as_deref(),
+            Some("ELLIPTIC")
+        );
+    }
+
+    #[test]
+    fn reports_approval_progress() {
+        let details = |logic: &str| -> TransactionDetails {
+            serde_json::from_value(json!({
+                "id": "1",
+                "assetId": "ETH",
+                "txHash": "",
+                "status": "PENDING_AUTHORIZATION",
+                "subStatus": "",
+                "signedMessages": [],
+                "signedBy": [],
+                "authorizationInfo": {
+                    "allowOperatorAsAuthorizer": false,
+                    "logic": logic,
+                    "groups": [
+                        { "th": 2, "users": { "a": "APPROVED", "b": "PENDING_AUTHORIZATION" } },
+                        { "th": 1, "users": { "c": "APPROVED" } }
+                    ]
+                }
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            details("AND").approval_progress(),
+            Some(ApprovalProgress {
+                collected: 2,
+                required: 3
+            })
+        );
+        assert_eq!(
+            details("OR").approval_progress(),
+            Some(ApprovalProgress {
+                collected: 1,
+                required: 1
+            })
+        );
+    }
+
+    #[test]
+    fn parses_vault_asset_summaries() {
+        let summary: VaultAssetSummary = serde_json::from_value(json!({
+            "assetId": "ETH",
+            "total": "12.5",
+            "available": "10",
+            "pending": "2.5",
+            "frozen": "0",
+        }))
+        .unwrap();
+        let amount = |amount: &str| amount.parse::<Amount>().unwrap();
+        assert_eq!(
+            summary.balances().unwrap(),
+            Balances {
+                total: amount("12.5"),
+                available: amount("10"),
+                pending: amount("2.5"),
+                locked: Amount::default(),
+            }
+        );
+
+        // workspace totals of 18 decimals assets must not be rounded
+        let summary: VaultAssetSummary = serde_json::from_value(json!({
+            "assetId": "ETH",
+            "total": "123456789.123456789123456789",
+            "available": "0.000000000000000001",
+        }))
+        .unwrap();
+        let balances = summary.balances().unwrap();
+        assert_eq!(balances.total.to_string(), "123456789.123456789123456789");
+        assert_eq!(balances.available.to_base_units(18), Some(1));
+        assert!(VaultAssetSummary {
+            total: "1.5 ETH".into(),
+            ..summary
+        }
+        .balances()
+        .is_err());
+    }
+
+    #[test]
+    fn round_trips_unknown_peer_types() {
+        let peer: TransferPeerPathResponse =
+            serde_json::from_value(json!({ "type": "UNKNOWN" })).unwrap();
+        assert_eq!(peer.peer_type, PeerType::Other("UNKNOWN".to_owned()));
+        let peer: TransferPeerPathResponse =
+            serde_json::from_value(json!({ "type": "END_USER_WALLET", "id": "7" })).unwrap();
+        assert_eq!(
+            serde_json::to_value(&peer.peer_type).unwrap(),
+            json!("END_USER_WALLET")
+        );
+        assert_eq!(
+            serde_json::to_value(PeerType::VAULT_ACCOUNT).unwrap(),
+            json!("VAULT_ACCOUNT")
+        );
+        assert_eq!(
+            serde_json::from_value::<PeerType>(json!("CONTRACT")).unwrap(),
+            PeerType::CONTRACT
+        );
+    }
+
+    #[test]
+    fn deserializes_exchange_accounts() {
+        let account: ExchangeAccount = serde_json::from_value(json!({
+            "id": "ex-1",
+            "type": "BINANCE",
+            "name": "Binance",
+            "status": "APPROVED",
+            "assets": [{ "id": "USDT", "total": "100", "available": "90", "lockedAmount": "10" }],
+            "isSubaccount": false,
+        }))
+        .unwrap();
+        assert_eq!(account.exchange_type, "BINANCE");
+        assert_eq!(account.assets[0].locked_amount.as_deref(), Some("10"));
+        assert_eq!(account.main_account_id, None);
+    }
+
+    #[test]
+    fn gas_station_configuration_bytes() {
+        let info: GasStationInfo = serde_json::from_value(json!({
+            "balance": { "ETH": "1.5" },
+            "configuration": { "gasThreshold": "0.005", "gasCap": "0.01" },
+        }))
+        .unwrap();
+        assert_eq!(info.balance["ETH"], "1.5");
+        assert_eq!(info.configuration.max_gas_price, None);
+        assert_eq!(
+            serde_json::to_string(&info.configuration).unwrap(),
+            r#"{"gasThreshold":"0.005","gasCap":"0.01"}"#
+        );
+    }
+
+    #[test]
+    fn deserializes_supported_assets() {
+        let assets: Vec<SupportedAsset> = serde_json::from_value(json!([
+            {
+                "id": "ETH",
+                "name": "Ethereum",
+                "type": "BASE_ASSET",
+                "contractAddress": "",
+                "nativeAsset": "ETH",
+                "decimals": 18,
+            },
+            {
+                "id": "USDC",
+                "name": "USD Coin",
+                "type": "ERC20",
+                "contractAddress": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                "nativeAsset": "ETH",
+                "decimals": 6,
+            },
+        ]))
+        .unwrap();
+        assert_eq!(assets[0].asset_type, "BASE_ASSET");
+        assert_eq!(assets[1].decimals, Some(6));
+        assert_eq!(assets[1].native_asset, "ETH");
+    }
+
+    #[test]
+    fn deserializes_network_fee_estimates() {
+        let fee = json!({
+            "networkFee": "0.000441",
+            "gasPrice": "21",
+            "gasLimit": "21000",
+            "baseFee": "20",
+            "priorityFee": "1",
+        });
+        let estimate: NetworkFeeEstimate = serde_json::from_value(json!({
+            "low": fee,
+            "medium": fee,
+            "high": { "feePerByte": "12", "networkFee": "0.0001" },
+        }))
+        .unwrap();
+        assert_eq!(estimate.low.base_fee.as_deref(), Some("20"));
+        assert_eq!(estimate.medium.priority_fee.as_deref(), Some("1"));
+        assert_eq!(estimate.high.fee_per_byte.as_deref(), Some("12"));
+        assert_eq!(estimate.high.gas_price, None);
+    }
+
+    #[test]
+    fn serializes_transaction_filter() {
+        let filter = TransactionFilter {
+            source_type: Some(PeerType::VAULT_ACCOUNT),
+            source_id: Some("0".to_owned()),
+            dest_type: Some(PeerType::EXTERNAL_WALLET),
+            tx_hash: Some("0xabc".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_urlencoded::to_string(&filter).unwrap(),
+            "sourceType=VAULT_ACCOUNT&sourceId=0&destType=EXTERNAL_WALLET&txHash=0xabc"
+        );
+        assert_eq!(
+            serde_urlencoded::to_string(TransactionFilter::default()).unwrap(),
+            ""
+        );
+    }
+
+    // Fireblocks is sensitive to the field order of some payloads, so these pin the exact
+    // bytes which are sent for each POST body.
+    #[test]
+    fn create_vault_request_bytes() {
+        let req = CreateVaultRequest {
+            name: "test-acc".to_owned(),
+            hidden_on_ui: false,
+            customer_ref_id: Some("customer".to_owned()),
+            auto_fuel: true,
+        };
+        assert_eq!(
+            serde_json::to_string(&req).unwrap(),
+            r#"{"name":"test-acc","hiddenOnUI":false,"customerRefId":"customer","autoFuel":true}"#
+        );
+    }
+
+    #[test]
+    fn drop_transaction_request_bytes() {
+        let req = DropTransactionRequest {
+            fee_level: Some(FeeLevel::HIGH),
+            gas_price: Some("120".to_owned()),
+        };
+        assert_eq!(
+            serde_json::to_string(&req).unwrap(),
+            r#"{"feeLevel":"HIGH","gasPrice":"120"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&DropTransactionRequest::default()).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn exchange_request_bytes() {
+        let transfer = ExchangeInternalTransferRequest {
+            asset: "USDT".to_owned(),
+            amount: "10".to_owned(),
+            source_type: "FUNDING".to_owned(),
+            dest_type: "EXCHANGE".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_string(&transfer).unwrap(),
+            r#"{"asset":"USDT","amount":"10","sourceType":"FUNDING","destType":"EXCHANGE"}"#
+        );
+        let convert = ExchangeConvertRequest {
+            src_asset: "USD".to_owned(),
+            dest_asset: "USDC".to_owned(),
+            amount: "10".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_string(&convert).unwrap(),
+            r#"{"srcAsset":"USD","destAsset":"USDC","amount":"10"}"#
+        );
+    }
+
+    #[test]
+    fn transaction_arguments_bytes() {
+        let args = TransactionArguments {
+            asset_id: "ETH_TEST3".to_owned(),
+            operation: TransactionOperation::CONTRACT_CALL,
+            source: TransferPeerPath {
+                peer_type: Some(PeerType::VAULT_ACCOUNT),
+                id: Some("0".to_owned()),
+            },
+            destination: Some(DestinationTransferPeerPath {
+                peer_type: PeerType::ONE_TIME_ADDRESS,
+                id: None,
+                one_time_address: Some(OneTimeAddress {
+                    address: "0xcBE74E21B070A979b9d6426b11e876d4cB618DaF".to_owned(),
+                    tag: None,
+                }),
+            }),
+            amount: "0".to_owned(),
+            extra_parameters: Some(ExtraParameters::ContractCallData("ead710c4".to_owned())),
+            gas_price: None,
+            gas_limit: Some("21000".to_owned()),
+            fee_level: None,
+            max_fee: None,
+            priority_fee: None,
+            note: "".to_owned(),
+            customer_ref_id: None,
+            external_tx_id: Some("order-1".to_owned()),
+            replace_tx_by_hash: None,
+            fail_on_low_fee: None,
+            force_sweep: None,
+            treat_as_gross_amount: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&args).unwrap(),
+            concat!(
+                r#"{"assetId":"ETH_TEST3","operation":"CONTRACT_CALL","#,
+                r#""source":{"type":"VAULT_ACCOUNT","id":"0"},"#,
+                r#""destination":{"type":"ONE_TIME_ADDRESS","#,
+                r#""oneTimeAddress":{"address":"0xcBE74E21B070A979b9d6426b11e876d4cB618DaF"}},"#,
+                r#""amount":"0","extraParameters":{"contractCallData":"ead710c4"},"#,
+                r#""gasLimit":"21000","note":"","externalTxId":"order-1"}"#
+            )
+        );
+
+        let args = TransactionArguments {
+            fail_on_low_fee: Some(true),
+            treat_as_gross_amount: Some(false),
+            ..args
+        };
+        let value = serde_json::to_value(&args).unwrap();
+        assert_eq!(value["failOnLowFee"], json!(true));
+        assert_eq!(value["treatAsGrossAmount"], json!(false));
+        assert!(value.get("forceSweep").is_none());
+    }
+
+    #[test]
+    fn serializes_typed_messages() {
+        let message =
+            UnsignedMessage::typed(TypedMessageType::EIP712, json!({ "primaryType": "Mail" }));
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({ "content": { "primaryType": "Mail" }, "type": "EIP712" })
+        );
+        assert_eq!(
+            serde_json::to_value(UnsignedMessage::raw(&[0xab, 0xcd])).unwrap(),
+            json!({ "content": "abcd" })
+        );
+        assert_eq!(
+            serde_json::to_value(UnsignedMessage::raw(&[0xab]).with_bip44(3, 0)).unwrap(),
+            json!({ "content": "ab", "bip44addressIndex": 3, "bip44change": 0 })
+        );
+    }
+
+    #[test]
+    fn extra_parameters_roundtrip() {
+        let params = ExtraParameters::ContractCallData("ead710c4".to_owned());
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value, json!({ "contractCallData": "ead710c4" }));
+
+        // unknown modes are preserved verbatim
+        let unknown = json!({ "someFutureMode": { "foo": 1 } });
+        let params: ExtraParameters = serde_json::from_value(unknown.clone()).unwrap();
+        assert!(matches!(params, ExtraParameters::Other(_)));
+        assert_eq!(serde_json::to_value(&params).unwrap(), unknown);
+    }
 }
@@ -0,0 +1,325 @@
+//! Intake of [Fireblocks webhook](https://developers.fireblocks.com/reference/webhooks-structures)
+//! events.
+//!
+//! Fireblocks delivers webhooks at least once, so the same event may arrive several times.
+//! [`WebhookDeduper`] drops the events which were already processed, keeping track of them
+//! in a pluggable [`EventStore`].
+//!
+//! [`WebhookDispatcher`] hands the transaction events to the signers waiting for those
+//! transactions, so that they do not have to poll for every status change.
+use crate::{jwtclient::body_hash, types::TransactionDetails, FireblocksError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+/// The type of the events which carry a transaction's new status
+const TRANSACTION_STATUS_UPDATED: &str = "TRANSACTION_STATUS_UPDATED";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A webhook event as posted by Fireblocks
+pub struct WebhookEvent {
+    /// The event type, e.g. `TRANSACTION_STATUS_UPDATED`
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub tenant_id: String,
+    /// Milliseconds since Epoch at which the event was emitted
+    pub timestamp: u64,
+    /// The event payload, e.g. the transaction details for transaction events
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    /// Parses an event from the raw webhook body
+    pub fn from_slice(body: &[u8]) -> Result<Self> {
+        serde_json::from_slice(body).map_err(|err| FireblocksError::SerdeJson {
+            err,
+            text: String::from_utf8_lossy(body).into_owned(),
+        })
+    }
+
+    /// A stable identifier of the event. Fireblocks does not assign event ids, so this is
+    /// the hash of the event's contents, which are identical across redeliveries.
+    pub fn id(&self) -> String {
+        // serializing a parsed event cannot fail
+        body_hash(self).expect("event is serializable")
+    }
+}
+
+#[async_trait]
+/// Remembers which events were already processed
+pub trait EventStore: std::fmt::Debug + Send + Sync {
+    /// Marks the event as processed, returning `false` if it had already been.
+    async fn insert(&self, event_id: &str) -> Result<bool>;
+}
+
+#[derive(Debug)]
+/// An in-memory [`EventStore`] which remembers the `capacity` most recent events
+pub struct MemoryEventStore {
+    capacity: usize,
+    seen: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl MemoryEventStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: Default::default(),
+        }
+    }
+}
+
+impl Default for MemoryEventStore {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+#[async_trait]
+impl EventStore for MemoryEventStore {
+    async fn insert(&self, event_id: &str) -> Result<bool> {
+        let mut guard = self.seen.lock().expect("event store lock poisoned");
+        let (seen, order) = &mut *guard;
+        if !seen.insert(event_id.to_owned()) {
+            return Ok(false);
+        }
+        order.push_back(event_id.to_owned());
+        if order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                seen.remove(&oldest);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Debug)]
+/// Parses webhook bodies, dropping the events which were already processed
+pub struct WebhookDeduper<S = MemoryEventStore> {
+    store: S,
+}
+
+impl Default for WebhookDeduper {
+    fn default() -> Self {
+        Self::new(MemoryEventStore::default())
+    }
+}
+
+impl<S: EventStore> WebhookDeduper<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Parses the raw webhook body. Returns `None` if the event was already processed.
+    pub async fn process(&self, body: &[u8]) -> Result<Option<WebhookEvent>> {
+        let event = WebhookEvent::from_slice(body)?;
+        Ok(if self.store.insert(&event.id()).await? {
+            Some(event)
+        } else {
+            None
+        })
+    }
+}
+
+type Waiters = Arc<Mutex<HashMap<String, Vec<mpsc::UnboundedSender<TransactionDetails>>>>>;
+
+#[derive(Debug, Clone)]
+/// Hands transaction status events to the signers waiting for the transactions. A signer
+/// with a dispatcher (see `FireblocksSigner::webhook_dispatcher`) waits for the events of the
+/// transactions it submits, and only polls Fireblocks if no event arrives within the
+/// dispatcher's fallback interval. Clones share the same waiters.
+///
+/// Webhook bodies are not authenticated, so signers treat events as a prompt to fetch the
+/// transaction rather than as its state.
+pub struct WebhookDispatcher {
+    waiters: Waiters,
+    fallback: Duration,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl WebhookDispatcher {
+    /// Creates a dispatcher whose waiters poll Fireblocks whenever no event arrives for
+    /// `fallback`, e.g. because a webhook delivery got lost
+    pub fn new(fallback: Duration) -> Self {
+        Self {
+            waiters: Default::default(),
+            fallback,
+        }
+    }
+
+    /// How long waiters wait for an event before polling
+    pub fn fallback(&self) -> Duration {
+        self.fallback
+    }
+
+    /// Starts waiting for the events of the transaction `tx_id`. The subscription stops
+    /// when it is dropped. A transaction may have several subscriptions.
+    pub fn subscribe(&self, tx_id: &str) -> WebhookSubscription {
+        let (sender, events) = mpsc::unbounded_channel();
+        self.waiters
+            .lock()
+            .expect("dispatcher lock poisoned")
+            .entry(tx_id.to_owned())
+            .or_default()
+            .push(sender.clone());
+        WebhookSubscription {
+            tx_id: tx_id.to_owned(),
+            sender,
+            events,
+            waiters: self.waiters.clone(),
+        }
+    }
+
+    /// Hands a transaction status event to the subscriptions of its transaction. Returns
+    /// whether the transaction had a subscription. Other events are ignored.
+    pub fn dispatch(&self, event: &WebhookEvent) -> Result<bool> {
+        if event.event_type != TRANSACTION_STATUS_UPDATED {
+            return Ok(false);
+        }
+        let details: TransactionDetails =
+            serde_json::from_value(event.data.clone()).map_err(|err| {
+                FireblocksError::SerdeJson {
+                    err,
+                    text: event.data.to_string(),
+                }
+            })?;
+        let waiters = self.waiters.lock().expect("dispatcher lock poisoned");
+        Ok(match waiters.get(&details.id) {
+            Some(senders) => {
+                // every subscription gets the event, so this must not short-circuit
+                let sent = senders
+                    .iter()
+                    .filter(|sender| sender.send(details.clone()).is_ok())
+                    .count();
+                sent > 0
+            }
+            None => false,
+        })
+    }
+}
+
+#[derive(Debug)]
+/// The events of a single transaction, see [`WebhookDispatcher::subscribe`]
+pub struct WebhookSubscription {
+    tx_id: String,
+    // identifies the subscription among the ones of the same transaction
+    sender: mpsc::UnboundedSender<TransactionDetails>,
+    events: mpsc::UnboundedReceiver<TransactionDetails>,
+    waiters: Waiters,
+}
+
+impl WebhookSubscription {
+    /// Waits up to `timeout` for the next event of the transaction, returning its details
+    pub async fn next(&mut self, timeout: Duration) -> Option<TransactionDetails> {
+        tokio::time::timeout(timeout, self.events.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+}
+
+impl Drop for WebhookSubscription {
+    fn drop(&mut self) {
+        if let Ok(mut waiters) = self.waiters.lock() {
+            if let Some(senders) = waiters.get_mut(&self.tx_id) {
+                senders.retain(|sender| !sender.same_channel(&self.sender));
+                if senders.is_empty() {
+                    waiters.remove(&self.tx_id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TransactionStatus;
+
+    #[tokio::test]
+    async fn dispatches_status_updates() {
+        let dispatcher = WebhookDispatcher::default();
+        let event = |id: &str, status: &str| WebhookEvent {
+            event_type: TRANSACTION_STATUS_UPDATED.to_owned(),
+            tenant_id: "tenant".to_owned(),
+            timestamp: 1,
+            data: serde_json::json!({
+                "id": id,
+                "assetId": "ETH",
+                "txHash": "",
+                "status": status,
+                "subStatus": "",
+                "signedMessages": [],
+            }),
+        };
+
+        let mut subscription = dispatcher.subscribe("tx");
+        assert!(dispatcher.dispatch(&event("tx", "BROADCASTING")).unwrap());
+        assert!(!dispatcher
+            .dispatch(&event("other", "BROADCASTING"))
+            .unwrap());
+        let details = subscription.next(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(details.status, TransactionStatus::BROADCASTING);
+        assert!(subscription.next(Duration::from_millis(10)).await.is_none());
+
+        // every subscription of a transaction gets its events, until it is dropped
+        let mut other = dispatcher.subscribe("tx");
+        assert!(dispatcher.dispatch(&event("tx", "CONFIRMING")).unwrap());
+        for subscription in [&mut subscription, &mut other] {
+            let details = subscription.next(Duration::from_secs(1)).await.unwrap();
+            assert_eq!(details.status, TransactionStatus::CONFIRMING);
+        }
+        drop(other);
+        assert!(dispatcher.dispatch(&event("tx", "CONFIRMING")).unwrap());
+        assert!(subscription.next(Duration::from_secs(1)).await.is_some());
+
+        drop(subscription);
+        assert!(!dispatcher.dispatch(&event("tx", "COMPLETED")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn drops_replayed_events() {
+        let deduper = WebhookDeduper::new(MemoryEventStore::new(1));
+        let event = |status: &str| {
+            serde_json::to_vec(&serde_json::json!({
+                "type": "TRANSACTION_STATUS_UPDATED",
+                "tenantId": "tenant",
+                "timestamp": 1,
+                "data": { "id": "tx", "status": status },
+            }))
+            .unwrap()
+        };
+
+        assert!(deduper
+            .process(&event("SUBMITTED"))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(deduper
+            .process(&event("SUBMITTED"))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(deduper
+            .process(&event("COMPLETED"))
+            .await
+            .unwrap()
+            .is_some());
+        // evicted once the capacity is exceeded
+        assert!(deduper
+            .process(&event("SUBMITTED"))
+            .await
+            .unwrap()
+            .is_some());
+    }
+}